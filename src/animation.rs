@@ -0,0 +1,364 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use image::codecs::gif::GifDecoder;
+use image::AnimationDecoder;
+use crate::assets::AssetError;
+use crate::textures::Texture;
+
+const DEFAULT_FRAME_MS: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoopMode {
+    Loop,     // Vuelve al primer cuadro al terminar
+    Once,     // Se queda en el último cuadro
+    PingPong, // Va y vuelve: 0 1 2 1 0 1 2 ...
+}
+
+// Secuencia de cuadros con la duración de cada uno. Una textura fija es una animación
+// de un solo cuadro. Con varias rotaciones cada cuadro tiene una imagen por ángulo de
+// vista, guardadas seguidas: la rotación 0 es de frente y las siguientes giran en el
+// sentido de los ángulos del mapa
+pub struct Animation {
+    frames: Vec<Arc<Texture>>,
+    durations: Vec<f32>, // Segundos que dura cada cuadro
+    rotations: usize,
+    pub loop_mode: LoopMode,
+}
+
+// Opciones de una línea `animation` del manifiesto: lo que tiene '=' es una opción y el
+// resto son rutas. Por ejemplo `grid=4x2 frames=6 frame_ms=80 loop=pingpong`
+struct Options<'a> {
+    paths: Vec<&'a str>,
+    grid: Option<(u32, u32)>,
+    frames: Option<usize>,
+    rotations: usize,
+    frame_ms: Vec<u32>,
+    loop_mode: LoopMode,
+}
+
+fn parse_options(args: &[String]) -> Result<Options<'_>, AssetError> {
+    let mut options = Options { paths: Vec::new(), grid: None, frames: None, rotations: 1, frame_ms: Vec::new(), loop_mode: LoopMode::Loop };
+    let source = args.first().map_or("animation", String::as_str);
+    let invalid = |arg: &str| AssetError::decode(source, format!("invalid animation option '{}'", arg));
+
+    for arg in args {
+        let Some((key, value)) = arg.split_once('=') else {
+            options.paths.push(arg);
+            continue;
+        };
+        match key {
+            "grid" => {
+                let (columns, rows) = value.split_once('x').ok_or_else(|| invalid(arg))?;
+                let grid = (columns.parse().map_err(|_| invalid(arg))?, rows.parse().map_err(|_| invalid(arg))?);
+                if grid.0 == 0 || grid.1 == 0 {
+                    return Err(invalid(arg));
+                }
+                options.grid = Some(grid);
+            }
+            "frames" => options.frames = Some(value.parse().map_err(|_| invalid(arg))?),
+            "rotations" => {
+                options.rotations = value.parse().map_err(|_| invalid(arg))?;
+                if options.rotations == 0 {
+                    return Err(invalid(arg));
+                }
+            }
+            "frame_ms" => {
+                options.frame_ms = value.split(',').map(|ms| ms.parse().map_err(|_| invalid(arg))).collect::<Result<_, _>>()?;
+            }
+            "loop" => {
+                options.loop_mode = match value {
+                    "loop" => LoopMode::Loop,
+                    "once" => LoopMode::Once,
+                    "pingpong" => LoopMode::PingPong,
+                    _ => return Err(invalid(arg)),
+                }
+            }
+            _ => return Err(invalid(arg)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err(AssetError::decode(source, "animation has no image files"));
+    }
+    Ok(options)
+}
+
+// Cuadros de un GIF animado con sus duraciones
+fn load_gif(path: &str) -> Result<Vec<(Texture, u32)>, AssetError> {
+    let file = File::open(path).map_err(|e| AssetError::io(path, e))?;
+    let decoder = GifDecoder::new(BufReader::new(file)).map_err(|e| AssetError::decode(path, e))?;
+    let frames = decoder.into_frames().collect_frames().map_err(|e| AssetError::decode(path, e))?;
+
+    Ok(frames
+        .iter()
+        .map(|frame| {
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            // Como los navegadores, un retraso de 0 se toma como la duración por defecto
+            let ms = if numerator == 0 { DEFAULT_FRAME_MS } else { numerator / denominator.max(1) };
+            (Texture::from_image(frame.buffer()), ms)
+        })
+        .collect())
+}
+
+// Corta una hoja de sprites en celdas iguales, de izquierda a derecha y de arriba abajo
+fn slice_sheet(sheet: &Texture, columns: u32, rows: u32, count: usize) -> Vec<Texture> {
+    let (width, height) = (sheet.width / columns, sheet.height / rows);
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .take(count)
+        .map(|(column, row)| Texture::from_fn(width, height, |x, y| sheet.get_pixel_color(column * width + x, row * height + y)))
+        .collect()
+}
+
+impl Animation {
+    // Carga la animación de una línea del manifiesto: un GIF, una hoja de sprites con
+    // `grid=`, o varias imágenes sueltas. `frame_ms=` reemplaza las duraciones del GIF.
+    // Con `rotations=8` cada grupo de 8 imágenes es un cuadro visto desde 8 ángulos; en una
+    // hoja de sprites eso es una fila por cuadro y una columna por ángulo
+    pub fn load(args: &[String]) -> Result<Animation, AssetError> {
+        let options = parse_options(args)?;
+        let first = options.paths[0];
+
+        let images: Vec<(Texture, u32)> = if let Some((columns, rows)) = options.grid {
+            let count = (columns * rows) as usize;
+            slice_sheet(&Texture::load(first)?, columns, rows, count).into_iter().map(|frame| (frame, DEFAULT_FRAME_MS)).collect()
+        } else if options.paths.len() == 1 && first.to_ascii_lowercase().ends_with(".gif") {
+            load_gif(first)?
+        } else {
+            options.paths.iter().map(|path| Ok((Texture::load(path)?, DEFAULT_FRAME_MS))).collect::<Result<_, AssetError>>()?
+        };
+        Animation::assemble(images, &options)
+    }
+
+    // Agrupa las imágenes en cuadros de `rotations` imágenes y aplica las opciones.
+    // `frames=` cuenta cuadros, así que con rotaciones se queda con frames * rotations imágenes
+    fn assemble(mut images: Vec<(Texture, u32)>, options: &Options) -> Result<Animation, AssetError> {
+        let first = options.paths[0];
+        let rotations = options.rotations;
+        if let Some(count) = options.frames {
+            images.truncate(count.max(1) * rotations);
+        }
+        if images.is_empty() {
+            return Err(AssetError::decode(first, "animation has no frames"));
+        }
+        if images.len() % rotations != 0 {
+            let message = format!("{} images do not split into {} rotations", images.len(), rotations);
+            return Err(AssetError::decode(first, message));
+        }
+
+        // Una sola duración vale para todos los cuadros; una lista se repite si es corta.
+        // Las imágenes de un mismo cuadro toman la duración de la primera
+        let mut durations: Vec<u32> = images.iter().step_by(rotations).map(|&(_, ms)| ms).collect();
+        if !options.frame_ms.is_empty() {
+            for (i, ms) in durations.iter_mut().enumerate() {
+                *ms = options.frame_ms[i % options.frame_ms.len()];
+            }
+        }
+
+        let durations = durations.iter().map(|&ms| ms.max(1) as f32 / 1000.0).collect();
+        let frames = images.into_iter().map(|(texture, _)| Arc::new(texture)).collect();
+        Ok(Animation { frames, durations, rotations, loop_mode: options.loop_mode })
+    }
+
+    // Animación hecha con texturas ya creadas, todas con la misma duración
+    pub fn from_frames(frames: Vec<Texture>, frame_seconds: f32, loop_mode: LoopMode) -> Animation {
+        Animation::from_rotations(frames, 1, frame_seconds, loop_mode)
+    }
+
+    // Como from_frames, pero cada cuadro son `rotations` imágenes seguidas, una por ángulo
+    // de vista empezando de frente
+    pub fn from_rotations(images: Vec<Texture>, rotations: usize, frame_seconds: f32, loop_mode: LoopMode) -> Animation {
+        debug_assert!(rotations > 0 && images.len() % rotations == 0, "{} images do not split into {} rotations", images.len(), rotations);
+        let durations = vec![frame_seconds; images.len() / rotations];
+        Animation { frames: images.into_iter().map(Arc::new).collect(), durations, rotations, loop_mode }
+    }
+
+    // Un solo cuadro con el tablero de "textura faltante"
+    pub fn missing() -> Animation {
+        Animation::from_frames(vec![Texture::missing()], 1.0, LoopMode::Loop)
+    }
+
+    // Duración de una pasada, de principio a fin
+    pub fn duration(&self) -> f32 {
+        self.durations.iter().sum()
+    }
+
+    // Índice del cuadro que se ve `time` segundos después de empezar
+    pub fn frame_index(&self, time: f32) -> usize {
+        let count = self.durations.len();
+        let duration = self.duration();
+        if count == 1 || duration <= 0.0 {
+            return 0;
+        }
+
+        let (mut t, reversed) = match self.loop_mode {
+            LoopMode::Once if time >= duration => return count - 1,
+            LoopMode::Once => (time.max(0.0), false),
+            LoopMode::Loop => (time.rem_euclid(duration), false),
+            LoopMode::PingPong => {
+                // La vuelta no repite el último ni el primer cuadro
+                let back = duration - self.durations[0] - self.durations[count - 1];
+                let t = time.rem_euclid(duration + back.max(0.0));
+                if t < duration { (t, false) } else { (t - duration, true) }
+            }
+        };
+
+        if reversed {
+            for index in (1..count - 1).rev() {
+                if t < self.durations[index] {
+                    return index;
+                }
+                t -= self.durations[index];
+            }
+            return 1;
+        }
+
+        for (index, &frame) in self.durations.iter().enumerate() {
+            if t < frame {
+                return index;
+            }
+            t -= frame;
+        }
+        count - 1
+    }
+
+    pub fn frame_at(&self, time: f32) -> Arc<Texture> {
+        self.frame_facing(time, 0.0)
+    }
+
+    // Cuadro visto desde `view_angle`: el ángulo desde el que se mira, relativo a hacia
+    // dónde apunta la entidad. 0 es de frente; se elige la rotación más cercana
+    pub fn frame_facing(&self, time: f32, view_angle: f32) -> Arc<Texture> {
+        let step = std::f32::consts::TAU / self.rotations as f32;
+        let rotation = (view_angle / step).round().rem_euclid(self.rotations as f32) as usize % self.rotations;
+        self.frames[self.frame_index(time) * self.rotations + rotation].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Animación de `count` cuadros de 0.1 s
+    fn animation(count: usize, loop_mode: LoopMode) -> Animation {
+        let frames = (0..count).map(|_| Texture::from_fn(1, 1, |_, _| 0)).collect();
+        Animation::from_frames(frames, 0.1, loop_mode)
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn once_stops_on_last_frame() {
+        let once = animation(3, LoopMode::Once);
+        assert_eq!(once.frame_index(-1.0), 0);
+        assert_eq!(once.frame_index(0.05), 0);
+        assert_eq!(once.frame_index(0.15), 1);
+        assert_eq!(once.frame_index(0.25), 2);
+        assert_eq!(once.frame_index(5.0), 2);
+    }
+
+    #[test]
+    fn loop_wraps_to_first_frame() {
+        let looped = animation(3, LoopMode::Loop);
+        assert_eq!(looped.frame_index(0.25), 2);
+        assert_eq!(looped.frame_index(0.35), 0);
+        assert_eq!(looped.frame_index(-0.05), 2);
+    }
+
+    #[test]
+    fn ping_pong_goes_back_without_repeating_ends() {
+        let ping_pong = animation(3, LoopMode::PingPong);
+        let sequence: Vec<usize> = [0.05, 0.15, 0.25, 0.35, 0.45, 0.55].iter().map(|&t| ping_pong.frame_index(t)).collect();
+        assert_eq!(sequence, [0, 1, 2, 1, 0, 1]);
+
+        let pair = animation(2, LoopMode::PingPong);
+        let sequence: Vec<usize> = [0.05, 0.15, 0.25, 0.35].iter().map(|&t| pair.frame_index(t)).collect();
+        assert_eq!(sequence, [0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn parses_sheet_options() {
+        let line = args("sprites/cagney.png grid=4x2 frames=6 rotations=8 frame_ms=80,120 loop=pingpong");
+        let Ok(options) = parse_options(&line) else {
+            panic!("valid options were rejected");
+        };
+        assert_eq!(options.paths, ["sprites/cagney.png"]);
+        assert_eq!(options.grid, Some((4, 2)));
+        assert_eq!(options.frames, Some(6));
+        assert_eq!(options.rotations, 8);
+        assert_eq!(options.frame_ms, [80, 120]);
+        assert!(options.loop_mode == LoopMode::PingPong);
+    }
+
+    // Imágenes de prueba: cada una es un texel con `cuadro * 10 + rotación`
+    fn rotation_images(frames: u32, rotations: u32) -> Vec<(Texture, u32)> {
+        (0..frames)
+            .flat_map(|frame| (0..rotations).map(move |rotation| (Texture::from_fn(1, 1, move |_, _| frame * 10 + rotation), 100)))
+            .collect()
+    }
+
+    fn texel(texture: &Texture) -> u32 {
+        texture.get_pixel_color(0, 0)
+    }
+
+    #[test]
+    fn groups_images_by_rotation() {
+        let line = args("walk.png rotations=8 frames=2 frame_ms=50");
+        let Ok(options) = parse_options(&line) else {
+            panic!("valid options were rejected");
+        };
+        let Ok(animation) = Animation::assemble(rotation_images(3, 8), &options) else {
+            panic!("24 images did not split into 8 rotations");
+        };
+        // `frames=2` se queda con dos cuadros completos, no con dos imágenes
+        assert_eq!(animation.durations, [0.05, 0.05]);
+        assert_eq!(texel(&animation.frame_facing(0.0, 0.0)), 0);
+        assert_eq!(texel(&animation.frame_facing(0.06, 0.0)), 10);
+
+        let uneven = args("walk.png rotations=8");
+        let Ok(options) = parse_options(&uneven) else {
+            panic!("valid options were rejected");
+        };
+        assert!(Animation::assemble(rotation_images(1, 12), &options).is_err());
+    }
+
+    #[test]
+    fn picks_each_of_eight_facings() {
+        let line = args("walk.png rotations=8");
+        let Ok(options) = parse_options(&line) else {
+            panic!("valid options were rejected");
+        };
+        let Ok(animation) = Animation::assemble(rotation_images(2, 8), &options) else {
+            panic!("16 images did not split into 8 rotations");
+        };
+
+        let step = std::f32::consts::TAU / 8.0;
+        for rotation in 0..8 {
+            let angle = rotation as f32 * step;
+            assert_eq!(texel(&animation.frame_facing(0.0, angle)), rotation, "facing {} degrees", angle.to_degrees());
+            // Se elige la rotación más cercana, y los ángulos se repiten cada vuelta
+            assert_eq!(texel(&animation.frame_facing(0.0, angle + step * 0.4)), rotation);
+            assert_eq!(texel(&animation.frame_facing(0.0, angle - std::f32::consts::TAU)), rotation);
+            assert_eq!(texel(&animation.frame_facing(0.15, angle)), 10 + rotation);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        for line in [
+            "a.png grid=4",
+            "a.png grid=0x2",
+            "a.png frames=six",
+            "a.png rotations=0",
+            "a.png frame_ms=80,fast",
+            "a.png loop=sometimes",
+            "a.png speed=2",
+            "grid=4x2 frames=6",
+        ] {
+            assert!(parse_options(&args(line)).is_err(), "accepted '{}'", line);
+        }
+    }
+}
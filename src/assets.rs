@@ -0,0 +1,404 @@
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::SystemTime;
+use once_cell::sync::Lazy;
+use crate::animation::Animation;
+use crate::maze;
+use crate::textures::Texture;
+
+const MANIFEST_PATH: &str = "assets/manifest.txt";
+// Copia del manifiesto dentro del ejecutable, por si falta el archivo
+const DEFAULT_MANIFEST: &str = include_str!("../assets/manifest.txt");
+
+const CHECKER_SIZE: u32 = 8;   // Lado de cada cuadro del tablero de "textura faltante"
+const CHECKER_A: u32 = 0xFFFF00FF;
+const CHECKER_B: u32 = 0xFF000000;
+
+// Assets que no se pudieron cargar, para el reporte al iniciar
+static FAILURES: Mutex<Vec<AssetError>> = Mutex::new(Vec::new());
+
+static MANAGER: Lazy<RwLock<AssetManager>> = Lazy::new(|| RwLock::new(AssetManager::new()));
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io { path: String, source: std::io::Error },     // El archivo no existe o no se puede leer
+    Decode { path: String, message: String },        // Formato no soportado o archivo dañado
+    Audio { path: String, message: String },         // Sin dispositivo de audio o audio inválido
+    Unknown { id: String },                          // El id no aparece en el manifiesto
+}
+
+impl AssetError {
+    pub fn io(path: &str, source: std::io::Error) -> Self {
+        AssetError::Io { path: path.to_string(), source }
+    }
+
+    pub fn decode(path: &str, error: impl fmt::Display) -> Self {
+        AssetError::Decode { path: path.to_string(), message: error.to_string() }
+    }
+
+    pub fn audio(path: &str, error: impl fmt::Display) -> Self {
+        AssetError::Audio { path: path.to_string(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Io { path, source } => write!(f, "{}: could not read file ({})", path, source),
+            AssetError::Decode { path, message } => write!(f, "{}: could not decode ({})", path, message),
+            AssetError::Audio { path, message } => write!(f, "{}: audio unavailable ({})", path, message),
+            AssetError::Unknown { id } => write!(f, "{}: not listed in {}", id, MANIFEST_PATH),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Guarda el error para el reporte; el juego sigue con un reemplazo
+pub fn record(error: AssetError) {
+    if let Ok(mut failures) = FAILURES.lock() {
+        failures.push(error);
+    }
+}
+
+// Tablero magenta y negro, fácil de ver en pantalla cuando falta una imagen
+pub fn checker(x: u32, y: u32) -> u32 {
+    if (x / CHECKER_SIZE + y / CHECKER_SIZE) % 2 == 0 { CHECKER_A } else { CHECKER_B }
+}
+
+// Imprime los assets que fallaron al iniciar el juego
+pub fn report() {
+    let failures = match FAILURES.lock() {
+        Ok(failures) => failures,
+        Err(_) => return,
+    };
+
+    if failures.is_empty() {
+        return;
+    }
+
+    eprintln!("{} asset(s) failed to load, using fallbacks:", failures.len());
+    for error in failures.iter() {
+        eprintln!("  - {}", error);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Texture,
+    Sound,
+    Font,
+    Level,
+    Animation,
+}
+
+struct ManifestEntry {
+    kind: Kind,
+    id: String,
+    paths: Vec<String>,
+}
+
+// Un nivel: el laberinto y la ruta de su archivo de alturas
+pub struct Level {
+    pub maze: Vec<Vec<char>>,
+    pub heights: Option<String>,
+}
+
+// Asset cargado, con la fecha de modificación de sus archivos para recargarlo si cambian
+struct Slot<T> {
+    id: String,
+    paths: Vec<String>,
+    modified: Vec<Option<SystemTime>>,
+    value: Arc<T>,
+}
+
+struct AssetManager {
+    manifest: Vec<ManifestEntry>,
+    textures: Vec<Slot<Texture>>,
+    sounds: Vec<Slot<Vec<u8>>>,
+    fonts: Vec<Slot<Vec<u8>>>,
+    levels: Vec<Slot<Level>>,
+    animations: Vec<Slot<Animation>>,
+}
+
+// Handles: índices baratos de copiar que siempre apuntan a la versión más reciente del asset
+#[derive(Clone, Copy)]
+pub struct TextureHandle(usize);
+#[derive(Clone, Copy)]
+pub struct SoundHandle(usize);
+#[derive(Clone, Copy)]
+pub struct FontHandle(usize);
+#[derive(Clone, Copy)]
+pub struct LevelHandle(usize);
+#[derive(Clone, Copy)]
+pub struct AnimationHandle(usize);
+
+fn parse_manifest(text: &str) -> Vec<ManifestEntry> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let kind = match fields.next()? {
+                "texture" => Kind::Texture,
+                "sound" => Kind::Sound,
+                "font" => Kind::Font,
+                "level" => Kind::Level,
+                "animation" => Kind::Animation,
+                other => {
+                    eprintln!("{}: unknown asset kind '{}'", MANIFEST_PATH, other);
+                    return None;
+                }
+            };
+            let id = fields.next()?.to_string();
+            let paths = fields.map(str::to_string).collect();
+            Some(ManifestEntry { kind, id, paths })
+        })
+        .collect()
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn read_bytes(paths: &[String]) -> Result<Vec<u8>, AssetError> {
+    fs::read(&paths[0]).map_err(|e| AssetError::io(&paths[0], e))
+}
+
+fn load_texture(paths: &[String]) -> Result<Texture, AssetError> {
+    Texture::load(&paths[0])
+}
+
+fn load_level(paths: &[String]) -> Result<Level, AssetError> {
+    Ok(Level {
+        maze: maze::load_maze(&paths[0])?,
+        heights: paths.get(1).cloned(),
+    })
+}
+
+fn fallback_level() -> Level {
+    Level { maze: maze::fallback_maze(), heights: None }
+}
+
+impl<T> Slot<T> {
+    // Si falla la carga se registra el error y se usa el reemplazo
+    fn load(id: &str, paths: Vec<String>, load: fn(&[String]) -> Result<T, AssetError>, fallback: fn() -> T) -> Self {
+        let value = if paths.is_empty() {
+            fallback()
+        } else {
+            load(&paths).unwrap_or_else(|error| {
+                record(error);
+                fallback()
+            })
+        };
+
+        Slot {
+            id: id.to_string(),
+            modified: paths.iter().map(|path| modified(path)).collect(),
+            paths,
+            value: Arc::new(value),
+        }
+    }
+
+    // Vuelve a cargar el asset si alguno de sus archivos cambió. Si la nueva versión
+    // no carga (por ejemplo, a medio guardar) se conserva la anterior
+    fn reload_if_changed(&mut self, load: fn(&[String]) -> Result<T, AssetError>) -> bool {
+        let current: Vec<Option<SystemTime>> = self.paths.iter().map(|path| modified(path)).collect();
+        if self.paths.is_empty() || current == self.modified {
+            return false;
+        }
+        self.modified = current;
+
+        match load(&self.paths) {
+            Ok(value) => {
+                self.value = Arc::new(value);
+                true
+            }
+            Err(error) => {
+                eprintln!("Reload failed: {}", error);
+                false
+            }
+        }
+    }
+}
+
+// Busca un asset ya cargado por id o lo carga desde el manifiesto
+fn find_or_load<T>(
+    slots: &mut Vec<Slot<T>>,
+    manifest: &[ManifestEntry],
+    kind: Kind,
+    id: &str,
+    load: fn(&[String]) -> Result<T, AssetError>,
+    fallback: fn() -> T,
+) -> usize {
+    if let Some(index) = slots.iter().position(|slot| slot.id == id) {
+        return index;
+    }
+
+    let paths = match manifest.iter().find(|entry| entry.kind == kind && entry.id == id) {
+        Some(entry) => entry.paths.clone(),
+        None => {
+            record(AssetError::Unknown { id: id.to_string() });
+            Vec::new()
+        }
+    };
+
+    slots.push(Slot::load(id, paths, load, fallback));
+    slots.len() - 1
+}
+
+impl AssetManager {
+    fn new() -> Self {
+        let text = fs::read_to_string(MANIFEST_PATH).unwrap_or_else(|e| {
+            record(AssetError::io(MANIFEST_PATH, e));
+            DEFAULT_MANIFEST.to_string()
+        });
+
+        AssetManager {
+            manifest: parse_manifest(&text),
+            textures: Vec::new(),
+            sounds: Vec::new(),
+            fonts: Vec::new(),
+            levels: Vec::new(),
+            animations: Vec::new(),
+        }
+    }
+}
+
+// Un lock envenenado sólo significa que otro hilo falló a medio cargar; los datos siguen sirviendo
+fn read() -> RwLockReadGuard<'static, AssetManager> {
+    MANAGER.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write() -> RwLockWriteGuard<'static, AssetManager> {
+    MANAGER.write().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn texture(id: &str) -> TextureHandle {
+    let mut guard = write();
+    let manager = &mut *guard;
+    TextureHandle(find_or_load(&mut manager.textures, &manager.manifest, Kind::Texture, id, load_texture, Texture::missing))
+}
+
+// Registra una textura generada por código para usarla igual que las del manifiesto
+pub fn insert_texture(id: &str, texture: Texture) -> TextureHandle {
+    let mut manager = write();
+    manager.textures.push(Slot {
+        id: id.to_string(),
+        paths: Vec::new(),
+        modified: Vec::new(),
+        value: Arc::new(texture),
+    });
+    TextureHandle(manager.textures.len() - 1)
+}
+
+pub fn sound(id: &str) -> SoundHandle {
+    let mut guard = write();
+    let manager = &mut *guard;
+    SoundHandle(find_or_load(&mut manager.sounds, &manager.manifest, Kind::Sound, id, read_bytes, Vec::new))
+}
+
+pub fn font(id: &str) -> FontHandle {
+    let mut guard = write();
+    let manager = &mut *guard;
+    FontHandle(find_or_load(&mut manager.fonts, &manager.manifest, Kind::Font, id, read_bytes, Vec::new))
+}
+
+pub fn level(id: &str) -> LevelHandle {
+    let mut guard = write();
+    let manager = &mut *guard;
+    LevelHandle(find_or_load(&mut manager.levels, &manager.manifest, Kind::Level, id, load_level, fallback_level))
+}
+
+// Carga un nivel sin reemplazo: para los modos sin ventana, que no tienen sentido en el
+// cuarto vacío y prefieren fallar con el error
+pub fn try_level(id: &str) -> Result<Level, AssetError> {
+    let manager = read();
+    match manager.manifest.iter().find(|entry| entry.kind == Kind::Level && entry.id == id && !entry.paths.is_empty()) {
+        Some(entry) => load_level(&entry.paths),
+        None => Err(AssetError::Unknown { id: id.to_string() }),
+    }
+}
+
+pub fn animation(id: &str) -> AnimationHandle {
+    let mut guard = write();
+    let manager = &mut *guard;
+    AnimationHandle(find_or_load(&mut manager.animations, &manager.manifest, Kind::Animation, id, Animation::load, Animation::missing))
+}
+
+// Registra una animación generada por código, como insert_texture
+pub fn insert_animation(id: &str, animation: Animation) -> AnimationHandle {
+    let mut manager = write();
+    manager.animations.push(Slot {
+        id: id.to_string(),
+        paths: Vec::new(),
+        modified: Vec::new(),
+        value: Arc::new(animation),
+    });
+    AnimationHandle(manager.animations.len() - 1)
+}
+
+// Recarga las texturas, animaciones y niveles cuyos archivos cambiaron en disco.
+// Devuelve los ids de los niveles recargados, que el juego tiene que reconstruir
+pub fn reload_changed() -> Vec<String> {
+    let mut manager = write();
+
+    for slot in manager.textures.iter_mut() {
+        slot.reload_if_changed(load_texture);
+    }
+    for slot in manager.animations.iter_mut() {
+        slot.reload_if_changed(Animation::load);
+    }
+
+    manager
+        .levels
+        .iter_mut()
+        .filter_map(|slot| slot.reload_if_changed(load_level).then(|| slot.id.clone()))
+        .collect()
+}
+
+impl TextureHandle {
+    pub fn get(self) -> Arc<Texture> {
+        read().textures[self.0].value.clone()
+    }
+}
+
+impl SoundHandle {
+    // Bytes del archivo de sonido, o None si no se pudo leer
+    pub fn bytes(self) -> Option<Arc<Vec<u8>>> {
+        let bytes = read().sounds[self.0].value.clone();
+        if bytes.is_empty() { None } else { Some(bytes) }
+    }
+
+    pub fn path(self) -> String {
+        let manager = read();
+        let slot = &manager.sounds[self.0];
+        slot.paths.first().cloned().unwrap_or_else(|| slot.id.clone())
+    }
+}
+
+impl FontHandle {
+    pub fn bytes(self) -> Arc<Vec<u8>> {
+        read().fonts[self.0].value.clone()
+    }
+}
+
+impl LevelHandle {
+    pub fn get(self) -> Arc<Level> {
+        read().levels[self.0].value.clone()
+    }
+}
+
+impl AnimationHandle {
+    pub fn get(self) -> Arc<Animation> {
+        read().animations[self.0].value.clone()
+    }
+}
@@ -1,33 +1,53 @@
 use rodio::{Decoder, OutputStream, Sink, Source};
-use std::fs::File;
-use std::io::BufReader;
+use std::io::Cursor;
 use std::sync::{Arc, Mutex};
+use crate::assets::{self, AssetError, SoundHandle};
 
+// Sin sink el reproductor queda en silencio: play, pause y set_volume no hacen nada
 pub struct AudioPlayer {
-    sink: Arc<Mutex<Sink>>,
-    _stream: OutputStream,
+    sink: Option<Arc<Mutex<Sink>>>,
+    _stream: Option<OutputStream>,
 }
 
 impl AudioPlayer {
-    pub fn new(music_file: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let (stream, stream_handle) = OutputStream::try_default()?;
-        let sink = Sink::try_new(&stream_handle)?;
+    // Prepara el sonido; si falla, registra el error y devuelve un reproductor silencioso
+    pub fn new(sound: SoundHandle) -> Self {
+        // Si no se pudo leer el archivo el administrador de assets ya registró el error
+        let Some(bytes) = sound.bytes() else {
+            return AudioPlayer::silent();
+        };
 
-        let file = BufReader::new(File::open(music_file)?);
-        let source = Decoder::new(file)?.repeat_infinite(); // Repetir el sonido infinitamente
+        AudioPlayer::load(&sound.path(), bytes.to_vec()).unwrap_or_else(|error| {
+            assets::record(error);
+            AudioPlayer::silent()
+        })
+    }
+
+    pub fn load(music_file: &str, bytes: Vec<u8>) -> Result<Self, AssetError> {
+        let (stream, stream_handle) = OutputStream::try_default().map_err(|e| AssetError::audio(music_file, e))?;
+        let sink = Sink::try_new(&stream_handle).map_err(|e| AssetError::audio(music_file, e))?;
+
+        let source = Decoder::new(Cursor::new(bytes))
+            .map_err(|e| AssetError::decode(music_file, e))?
+            .repeat_infinite(); // Repetir el sonido infinitamente
         sink.append(source);
         sink.set_volume(0.5);
 
         sink.pause();
 
         Ok(AudioPlayer {
-            sink: Arc::new(Mutex::new(sink)),
-            _stream: stream,
+            sink: Some(Arc::new(Mutex::new(sink))),
+            _stream: Some(stream),
         })
     }
 
+    pub fn silent() -> Self {
+        AudioPlayer { sink: None, _stream: None }
+    }
+
     pub fn play(&self) {
-        if let Ok(mut sink) = self.sink.lock() {
+        let Some(sink) = &self.sink else { return };
+        if let Ok(sink) = sink.lock() {
             sink.play();
         } else {
             eprintln!("Failed to lock the sink for playback.");
@@ -35,7 +55,8 @@ impl AudioPlayer {
     }
 
     pub fn pause(&self) {
-        if let Ok(mut sink) = self.sink.lock() {
+        let Some(sink) = &self.sink else { return };
+        if let Ok(sink) = sink.lock() {
             sink.pause();
         } else {
             eprintln!("Failed to lock the sink to stop playback.");
@@ -43,7 +64,8 @@ impl AudioPlayer {
     }
 
     pub fn set_volume(&self, volume: f32) {
-        if let Ok(mut sink) = self.sink.lock() {
+        let Some(sink) = &self.sink else { return };
+        if let Ok(sink) = sink.lock() {
             sink.set_volume(volume);
         } else {
             eprintln!("Failed to lock the sink to set volume.");
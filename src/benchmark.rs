@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Instant;
+use nalgebra::Vector2;
+use crate::assets::{self, AssetError};
+use crate::{horizon, render3d, render_minimap, world_sprites, textures, WallTextures, HEIGHT, MINIMAP_OPACITY, WIDTH};
+use crate::camera::Camera;
+use crate::capture::{CaptureError, RecordFormat, Recorder};
+use crate::compositor::{Compositor, Layer};
+use crate::enemies::Enemies;
+use crate::framebuffer::Framebuffer;
+use crate::items::{ItemKind, Items};
+use crate::checkpoints::Checkpoints;
+use crate::player::Player;
+use crate::postfx::PostFx;
+use crate::raycasting::cast_ray;
+use crate::replay::{self, ReplayError, ReplayFrame};
+use crate::textures::Texture;
+use crate::weapons::{Projectiles, Weapons};
+use crate::world::World;
+
+const FRAMES: usize = 200;
+// La partida se repite con el mismo paso fijo que el límite de cuadros del juego, y se
+// graba uno de cada REPLAY_STEPS_PER_FRAME cuadros, unos 30 por segundo
+const REPLAY_DT: f32 = 1.0 / 60.0;
+const REPLAY_STEPS_PER_FRAME: usize = 2;
+const REPLAY_FRAME_MS: u32 = 33;
+
+// Lo que puede hacer fallar a los modos sin ventana
+#[derive(Debug)]
+pub enum HeadlessError {
+    Asset(AssetError),
+    Replay(ReplayError),
+    Capture(CaptureError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::Asset(error) => error.fmt(f),
+            HeadlessError::Replay(error) => error.fmt(f),
+            HeadlessError::Capture(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HeadlessError::Asset(error) => Some(error),
+            HeadlessError::Replay(error) => Some(error),
+            HeadlessError::Capture(error) => Some(error),
+        }
+    }
+}
+
+impl From<AssetError> for HeadlessError {
+    fn from(error: AssetError) -> Self {
+        HeadlessError::Asset(error)
+    }
+}
+
+impl From<ReplayError> for HeadlessError {
+    fn from(error: ReplayError) -> Self {
+        HeadlessError::Replay(error)
+    }
+}
+
+impl From<CaptureError> for HeadlessError {
+    fn from(error: CaptureError) -> Self {
+        HeadlessError::Capture(error)
+    }
+}
+
+// El nivel principal armado igual que al empezar el juego
+struct Scene {
+    world: World,
+    enemies: Enemies,
+    items: Items,
+    checkpoints: Checkpoints,
+    weapons: Weapons,
+    projectiles: Projectiles,
+}
+
+impl Scene {
+    fn load() -> Result<Self, AssetError> {
+        let level = assets::try_level("main")?;
+        let mut maze = level.maze;
+        let items = Items::from_maze(&mut maze);
+        Ok(Scene {
+            enemies: Enemies::spawn(),
+            checkpoints: Checkpoints::from_maze(&maze),
+            weapons: Weapons::new(),
+            projectiles: Projectiles::new(),
+            items,
+            world: World::new(maze, level.heights.as_deref().unwrap_or_default(), false),
+        })
+    }
+
+    fn render(&self, framebuffer: &mut Framebuffer, player: &Player, time: f32) {
+        let mut sprites = world_sprites(&self.enemies, &self.items, &self.checkpoints, player.pos);
+        sprites.extend(self.projectiles.sprites());
+        render3d(framebuffer, &self.world, &sprites, player, 0, time);
+    }
+
+    // Avanza un cuadro de una partida grabada en el mismo orden que el bucle del juego. El
+    // jugador queda donde lo puso la grabación, así que los golpes de los enemigos sólo
+    // se ven como destellos
+    fn step(&mut self, player: &mut Player, frame: &ReplayFrame, dt: f32) {
+        let world = &mut self.world;
+        frame.apply(player);
+        if frame.interact {
+            world.doors.interact(player);
+        }
+        world.doors.update(dt);
+
+        for item in self.items.collect(player) {
+            world.lighting.flash(item.pos, 1.0, 0.5);
+            if item.kind == ItemKind::PowerUp {
+                self.weapons.refill();
+            }
+        }
+        if self.checkpoints.update(player, &self.items, &self.enemies, &world.doors, &self.weapons) {
+            world.lighting.flash(player.pos, 1.0, 0.5);
+        }
+        world.lighting.torch = frame.torch;
+        world.lighting.update(dt, player);
+
+        self.weapons.select(frame.weapon);
+        self.weapons.update(dt);
+        if frame.fire && self.weapons.fire(player, world, &mut self.projectiles) {
+            world.lighting.flash(player.pos, 0.4, 0.1);
+        }
+        self.projectiles.update(dt, world, &mut self.enemies);
+
+        player.update(dt);
+        let (_, strikes) = self.enemies.update(dt, world, player);
+        for pos in strikes {
+            world.lighting.flash(pos, 0.8, 0.2);
+        }
+    }
+
+    // Las columnas de pared que render3d dibuja desde `player`, ya proyectadas, con las
+    // texturas completas y sin filtrar
+    fn wall_spans(&self, player: &Player, wall_textures: &WallTextures) -> Vec<WallSpan> {
+        let World { maze, walls, heights, doors, .. } = &self.world;
+        let camera = Camera::new(player, WIDTH, HEIGHT);
+        let camera_z = heights.camera_z(player);
+        let horizon = horizon(player);
+        let mut spans = Vec::new();
+
+        for column in 0..WIDTH {
+            let ray = camera.ray_dir(column);
+            for hit in cast_ray(maze, walls, heights, doors, player, camera.ray_angle(column), 0) {
+                let distance = camera.distance(&ray, hit.distance);
+                let top = camera.project_y(hit.height, camera_z, distance, horizon);
+                let bottom = camera.project_y(0.0, camera_z, distance, horizon);
+                let texture = wall_textures.wall(hit.wall_type);
+                let pixels_per_unit = (bottom - top) / hit.height.max(f32::EPSILON);
+
+                spans.push(WallSpan {
+                    column,
+                    wall_type: hit.wall_type,
+                    texel_x: (hit.tx.rem_euclid(1.0) * texture.width as f32) as u32,
+                    top,
+                    rows: top.max(0.0) as usize..(bottom.max(0.0) as usize).min(HEIGHT - 1),
+                    step: texture.height as f32 / pixels_per_unit,
+                });
+            }
+        }
+        spans
+    }
+}
+
+// Una columna de pared en pantalla, para medir sólo la lectura de sus texels
+struct WallSpan {
+    column: usize,
+    wall_type: char,
+    texel_x: u32,
+    top: f32,           // Fila de pantalla del borde superior, puede quedar fuera de la pantalla
+    rows: Range<usize>, // Filas de pantalla visibles
+    step: f32,          // Texels que se avanzan por fila de pantalla
+}
+
+// Copia de los texels como se guardaban antes de usar un solo buffer: un Vec por columna,
+// indexado [x][y]
+fn nested_texels(texture: &Texture) -> Vec<Vec<u32>> {
+    (0..texture.width)
+        .map(|x| (0..texture.height).map(|y| texture.get_pixel_color(x, y)).collect())
+        .collect()
+}
+
+// Recorrido fijo de la cámara del benchmark: una vuelta completa en el lugar de inicio
+fn spin_angle(frame: usize, frames: usize) -> f32 {
+    frame as f32 * std::f32::consts::TAU / frames as f32
+}
+
+// Mide `frame` FRAMES veces e imprime el promedio, la mediana y el mejor tiempo
+fn measure(label: &str, mut frame: impl FnMut(usize)) {
+    let mut times: Vec<f64> = (0..FRAMES)
+        .map(|i| {
+            let start = Instant::now();
+            frame(i);
+            start.elapsed().as_secs_f64() * 1000.0
+        })
+        .collect();
+    times.sort_by(|a, b| a.total_cmp(b));
+
+    let average = times.iter().sum::<f64>() / FRAMES as f64;
+    println!(
+        "{}: average {:.2} ms, median {:.2} ms, best {:.2} ms over {} frames",
+        label, average, times[FRAMES / 2], times[0], FRAMES
+    );
+}
+
+// Modo sin ventana (`cargo run --release -- --bench`): dibuja varios cuadros girando
+// en el mismo lugar y mide cuánto tarda render3d con cada modo de filtrado, el filtro de
+// película completo y cada uno de sus efectos, y la lectura de texels de las paredes con
+// cada forma de guardar las texturas. Falla si no se puede cargar el nivel
+pub fn run() -> Result<(), HeadlessError> {
+    let scene = Scene::load()?;
+    let mut player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+
+    // El primer cuadro carga las texturas, así que no se mide
+    scene.render(&mut framebuffer, &player, 0.0);
+
+    for (name, bilinear, mipmaps) in [("nearest", false, false), ("bilinear", true, false), ("bilinear + mipmaps", true, true)] {
+        textures::set_bilinear(bilinear);
+        textures::set_mipmaps(mipmaps);
+
+        measure(&format!("render3d {}x{} ({})", WIDTH, HEIGHT, name), |i| {
+            player.a = spin_angle(i, FRAMES);
+            scene.render(&mut framebuffer, &player, 0.0);
+        });
+    }
+
+    // El filtro trabaja siempre sobre una copia del mismo cuadro; la medición con el
+    // filtro apagado da el costo de esa copia, que hay que restar a las demás
+    let frame = framebuffer.buffer.clone();
+    let mut postfx = PostFx::new();
+    postfx.enabled = true;
+    postfx.apply(&mut framebuffer);
+
+    for (name, enabled) in [("off, frame copy only", false), ("all effects", true)] {
+        postfx.enabled = enabled;
+        measure(&format!("post-processing ({})", name), |_| {
+            framebuffer.buffer.copy_from_slice(&frame);
+            postfx.apply(&mut framebuffer);
+        });
+    }
+
+    let count = postfx.effects().len();
+    for only in 0..count {
+        let mut name = "";
+        for (i, (effect, enabled)) in postfx.effects().into_iter().enumerate() {
+            *enabled = i == only;
+            if i == only {
+                name = effect;
+            }
+        }
+        measure(&format!("post-processing ({})", name), |_| {
+            framebuffer.buffer.copy_from_slice(&frame);
+            postfx.apply(&mut framebuffer);
+        });
+    }
+
+    // Antes y después del buffer por columnas: lee los texels de las mismas columnas de
+    // pared con el buffer actual y con la copia en Vec<Vec<u32>>. Las columnas se
+    // proyectan antes de medir, así sólo cuenta el acceso a la textura
+    let wall_textures = WallTextures::resolve(0.0);
+    let frames: Vec<Vec<WallSpan>> = (0..FRAMES)
+        .map(|i| {
+            player.a = spin_angle(i, FRAMES);
+            scene.wall_spans(&player, &wall_textures)
+        })
+        .collect();
+    let mut layouts: HashMap<char, (&Texture, Vec<Vec<u32>>)> = HashMap::new();
+    for span in frames.iter().flatten() {
+        let texture = wall_textures.wall(span.wall_type);
+        layouts.entry(span.wall_type).or_insert_with(|| (texture, nested_texels(texture)));
+    }
+
+    measure("wall texels (flat column-major buffer)", |i| {
+        for span in &frames[i] {
+            let (texture, _) = &layouts[&span.wall_type];
+            let texels = texture.column(span.texel_x);
+            for y in span.rows.clone() {
+                let ty = ((y as f32 - span.top) * span.step) as u32;
+                framebuffer.buffer[y * WIDTH + span.column] = texels[texture.wrap_y(ty)];
+            }
+        }
+    });
+    measure("wall texels (Vec<Vec<u32>> [x][y])", |i| {
+        for span in &frames[i] {
+            let (texture, columns) = &layouts[&span.wall_type];
+            for y in span.rows.clone() {
+                let ty = ((y as f32 - span.top) * span.step) as u32;
+                framebuffer.buffer[y * WIDTH + span.column] = columns[(span.texel_x % texture.width) as usize][(ty % texture.height) as usize];
+            }
+        }
+    });
+    Ok(())
+}
+
+// Modo sin ventana (`cargo run --release -- --record partida.replay demo.gif`): repite una
+// partida guardada con --save-replay con un paso fijo, así cada vez sale igual, y la graba
+// con el minimapa y el filtro de película sin saltarse ningún cuadro. Con una ruta sin .gif
+// guarda una secuencia de PNG en esa carpeta
+pub fn record(replay_path: &Path, path: &Path) -> Result<(), HeadlessError> {
+    let frames = replay::load(replay_path)?;
+    let mut scene = Scene::load()?;
+    let mut player = Player::new(frames[0].pos, frames[0].angle);
+    let mut compositor = Compositor::new(WIDTH, HEIGHT);
+    compositor.layer(Layer::Minimap).opacity = MINIMAP_OPACITY;
+    let mut postfx = PostFx::new();
+    let mut recorder = Recorder::start(RecordFormat::for_path(path), path.to_path_buf())?;
+
+    for (i, frame) in frames.iter().enumerate() {
+        scene.step(&mut player, frame, REPLAY_DT);
+        if i % REPLAY_STEPS_PER_FRAME != 0 {
+            continue;
+        }
+
+        compositor.clear();
+        scene.render(compositor.target(Layer::World), &player, i as f32 * REPLAY_DT);
+        render_minimap(compositor.target(Layer::Minimap), &scene.world.maze, &scene.items, &player);
+
+        let frame = compositor.composite();
+        postfx.apply(frame);
+        recorder.push_with_delay(frame, REPLAY_FRAME_MS);
+    }
+
+    let (path, count) = recorder.finish()?;
+    eprintln!("Recorded {} frames to {}", count, path.display());
+    Ok(())
+}
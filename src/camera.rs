@@ -0,0 +1,223 @@
+use std::f32::consts::PI;
+use nalgebra::Vector2;
+use crate::player::Player;
+
+pub const MIN_FOV: f32 = 30.0 * PI / 180.0;
+pub const MAX_FOV: f32 = 170.0 * PI / 180.0; // La rectilínea necesita tan(fov / 2) finito
+
+// Cómo se reparten los rayos por la pantalla
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    Rectilinear, // Como una cámara común: las rectas del mundo quedan rectas
+    Cylindrical, // Panorámica: el ángulo avanza parejo por la pantalla y la altura usa la distancia real
+    Fisheye,     // Como la cilíndrica, pero también curva la vertical: las paredes se abomban
+}
+
+impl Projection {
+    pub fn name(self) -> &'static str {
+        match self {
+            Projection::Rectilinear => "Rectilinear",
+            Projection::Cylindrical => "Cylindrical",
+            Projection::Fisheye => "Fisheye",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Projection::Rectilinear => Projection::Cylindrical,
+            Projection::Cylindrical => Projection::Fisheye,
+            Projection::Fisheye => Projection::Rectilinear,
+        }
+    }
+}
+
+// Cámara con vector de dirección y plano de proyección, armada a partir del jugador en
+// cada cuadro. Paredes, piso y sprites usan los mismos rayos y la misma distancia, así
+// quedan alineados columna por columna con cualquier proyección
+pub struct Camera {
+    pub pos: Vector2<f32>,
+    pub dir: Vector2<f32>,   // Unitario, hacia donde mira el jugador
+    pub plane: Vector2<f32>, // Perpendicular a `dir`, de largo tan(fov / 2), hacia la derecha de la pantalla
+    pub projection: Projection,
+    angle: f32,
+    half_fov: f32,
+    width: usize,
+    focal_length: f32, // Pixeles de pantalla por unidad de `rise` en project_y
+}
+
+impl Camera {
+    pub fn new(player: &Player, width: usize, height: usize) -> Self {
+        let half_fov = player.fov.clamp(MIN_FOV, MAX_FOV) / 2.0;
+        let dir = Vector2::new(player.a.cos(), player.a.sin());
+        let plane = Vector2::new(-dir.y, dir.x) * half_fov.tan();
+
+        // La escala vertical sale del mismo campo de visión que la horizontal: el plano va de
+        // -1 a 1 a lo ancho, y a lo alto mide lo mismo por la proporción de la pantalla, así
+        // los pixeles quedan cuadrados con cualquier fov y tamaño de ventana
+        let half_extent = match player.projection {
+            Projection::Rectilinear => half_fov.tan(),
+            Projection::Cylindrical | Projection::Fisheye => half_fov,
+        };
+        let aspect = height as f32 / width as f32;
+        let focal_length = (height as f32 / 2.0) / (half_extent * aspect);
+
+        Camera { pos: player.pos, dir, plane, projection: player.projection, angle: player.a, half_fov, width, focal_length }
+    }
+
+    // Dirección del rayo de una columna. Avanzar `t` por ella aleja el punto `t` unidades
+    // de la cámara según la distancia que usa la proyección: la profundidad a lo largo de
+    // `dir` en la rectilínea, la distancia real en las demás
+    pub fn ray_dir(&self, column: usize) -> Vector2<f32> {
+        let x = 2.0 * column as f32 / self.width as f32 - 1.0;
+        match self.projection {
+            Projection::Rectilinear => self.dir + self.plane * x,
+            Projection::Cylindrical | Projection::Fisheye => {
+                let a = self.angle + x * self.half_fov;
+                Vector2::new(a.cos(), a.sin())
+            }
+        }
+    }
+
+    pub fn ray_angle(&self, column: usize) -> f32 {
+        let ray = self.ray_dir(column);
+        ray.y.atan2(ray.x)
+    }
+
+    // Pasa la distancia real a un punto sobre el rayo a la distancia de la proyección
+    pub fn distance(&self, ray: &Vector2<f32>, ray_distance: f32) -> f32 {
+        ray_distance / ray.norm()
+    }
+
+    // Columna de pantalla (puede caer fuera) y distancia de un punto del mundo.
+    // None si no se puede proyectar, como un punto detrás de la cámara rectilínea
+    pub fn project(&self, point: Vector2<f32>) -> Option<(f32, f32)> {
+        let relative = point - self.pos;
+        let forward = relative.dot(&self.dir);
+        let side = relative.dot(&self.plane) / self.plane.norm();
+        let (x, distance) = match self.projection {
+            Projection::Rectilinear if forward <= 0.0 => return None,
+            Projection::Rectilinear => (side / (forward * self.half_fov.tan()), forward),
+            Projection::Cylindrical | Projection::Fisheye => (side.atan2(forward) / self.half_fov, relative.norm()),
+        };
+        if distance <= 0.0 {
+            return None;
+        }
+        Some((self.width as f32 / 2.0 * (1.0 + x), distance))
+    }
+
+    // Columnas de los bordes izquierdo y derecho de un objeto de `width` unidades de ancho
+    // centrado en `point` y de frente a la cámara. Con los dos bordes proyectados, el ancho
+    // en pantalla sigue la escala horizontal de la proyección. None si algún borde no se
+    // puede proyectar
+    pub fn project_span(&self, point: Vector2<f32>, width: f32) -> Option<(f32, f32)> {
+        let relative = point - self.pos;
+        let side = Vector2::new(-relative.y, relative.x).try_normalize(f32::EPSILON)? * (width / 2.0);
+        let (left, _) = self.project(point - side)?;
+        let (right, _) = self.project(point + side)?;
+        Some((left, right))
+    }
+
+    // Posición vertical en pantalla de un punto a altura z visto a cierta distancia
+    pub fn project_y(&self, z: f32, camera_z: f32, distance: f32, horizon: f32) -> f32 {
+        let rise = (z - camera_z) / distance;
+        match self.projection {
+            Projection::Fisheye => horizon - rise.atan() * self.focal_length,
+            _ => horizon - rise * self.focal_length,
+        }
+    }
+
+    // Al revés que project_y: distancia a la que se ve, en la fila `y`, un plano que está
+    // `drop` unidades por debajo de la cámara
+    pub fn row_distance(&self, drop: f32, y: f32, horizon: f32) -> f32 {
+        let angle = (y - horizon) / self.focal_length;
+        match self.projection {
+            Projection::Fisheye => drop / angle.min(PI / 2.0 - 0.001).tan(),
+            _ => drop / angle,
+        }
+    }
+
+    // Pixeles de pantalla que ocupa una unidad del mundo en esa fila del plano, para
+    // elegir el mipmap
+    pub fn row_scale(&self, drop: f32, distance: f32) -> f32 {
+        drop * self.focal_length / (distance * distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 100;
+
+    // Cámara en el origen mirando hacia +x con 60° de campo de visión
+    fn camera(projection: Projection) -> Camera {
+        let mut player = Player::new(Vector2::new(0.0, 0.0), 0.0);
+        player.fov = PI / 3.0;
+        player.projection = projection;
+        Camera::new(&player, WIDTH, 100)
+    }
+
+    fn assert_projects(camera: &Camera, point: Vector2<f32>, column: f32, distance: f32) {
+        let Some((x, d)) = camera.project(point) else {
+            panic!("{:?} was not projected", point);
+        };
+        assert!((x - column).abs() < 1e-3, "column {} instead of {}", x, column);
+        assert!((d - distance).abs() < 1e-4, "distance {} instead of {}", d, distance);
+    }
+
+    #[test]
+    fn rectilinear_uses_depth_along_view() {
+        let camera = camera(Projection::Rectilinear);
+        let edge = (PI / 6.0).tan();
+        assert_projects(&camera, Vector2::new(2.0, 0.0), 50.0, 2.0);
+        assert_projects(&camera, Vector2::new(2.0, 2.0 * edge), 100.0, 2.0);
+        assert_projects(&camera, Vector2::new(2.0, -edge), 25.0, 2.0);
+        assert!(camera.project(Vector2::new(-1.0, 0.5)).is_none());
+        assert!(camera.project(Vector2::new(0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn span_uses_horizontal_scale() {
+        let camera = camera(Projection::Rectilinear);
+        let edge = (PI / 6.0).tan();
+        // Un objeto que llena la mitad del ancho de la vista ocupa media pantalla
+        let Some((left, right)) = camera.project_span(Vector2::new(2.0, 0.0), 2.0 * edge) else {
+            panic!("span was not projected");
+        };
+        assert!((left - 25.0).abs() < 1e-3 && (right - 75.0).abs() < 1e-3, "span {}..{}", left, right);
+    }
+
+    #[test]
+    fn vertical_scale_matches_horizontal() {
+        // En una ventana más ancha que alta, un punto a la altura del borde del campo de
+        // visión queda a medio ancho del horizonte, igual que el borde horizontal queda a
+        // medio ancho del centro: los pixeles son cuadrados
+        let (width, height) = (crate::WIDTH, crate::HEIGHT);
+        let horizon = height as f32 / 2.0;
+        let half_width = width as f32 / 2.0;
+        let rise = (PI / 6.0).tan();
+        for projection in [Projection::Rectilinear, Projection::Fisheye] {
+            let mut player = Player::new(Vector2::new(0.0, 0.0), 0.0);
+            player.fov = PI / 3.0;
+            player.projection = projection;
+            let camera = Camera::new(&player, width, height);
+
+            let y = camera.project_y(2.0 * rise, 0.0, 2.0, horizon);
+            assert!((y - (horizon - half_width)).abs() < 1e-2, "row {} instead of {}", y, horizon - half_width);
+            let distance = camera.row_distance(1.0, horizon + half_width, horizon);
+            assert!((distance - 1.0 / rise).abs() < 1e-3, "distance {} instead of {}", distance, 1.0 / rise);
+        }
+    }
+
+    #[test]
+    fn angular_projections_use_real_distance() {
+        for projection in [Projection::Cylindrical, Projection::Fisheye] {
+            let camera = camera(projection);
+            let edge = Vector2::new((PI / 6.0).cos(), (PI / 6.0).sin()) * 3.0;
+            assert_projects(&camera, Vector2::new(2.0, 0.0), 50.0, 2.0);
+            assert_projects(&camera, edge, 100.0, 3.0);
+            // A 90° de la vista cae fuera de la pantalla, pero sigue teniendo columna
+            assert_projects(&camera, Vector2::new(0.0, -2.0), -100.0, 2.0);
+        }
+    }
+}
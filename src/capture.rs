@@ -0,0 +1,237 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbImage, RgbaImage};
+use crate::framebuffer::Framebuffer;
+
+const CAPTURE_DIR: &str = "captures";
+const GIF_SPEED: i32 = 20;     // Calidad de la paleta del GIF: 1 es la mejor, 30 la más rápida
+const QUEUE_FRAMES: usize = 8; // Cuadros en espera de codificar antes de empezar a saltarse algunos
+// Los GIF se graban a la mitad del tamaño: el grano de película casi no se comprime y a
+// tamaño completo cada segundo ocupa decenas de megabytes
+const GIF_DOWNSCALE: u32 = 2;
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io { path: PathBuf, source: std::io::Error },    // No se pudo crear o escribir el archivo
+    Encode { path: PathBuf, message: String },       // El codificador de imagen falló
+}
+
+impl CaptureError {
+    fn io(path: &Path, source: std::io::Error) -> Self {
+        CaptureError::Io { path: path.to_path_buf(), source }
+    }
+
+    fn encode(path: &Path, error: impl fmt::Display) -> Self {
+        CaptureError::Encode { path: path.to_path_buf(), message: error.to_string() }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CaptureError::Io { path, source } => write!(f, "{}: could not write file ({})", path.display(), source),
+            CaptureError::Encode { path, message } => write!(f, "{}: could not encode ({})", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CaptureError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RecordFormat {
+    Gif,         // Un solo GIF animado
+    PngSequence, // Una carpeta con frame_00000.png, frame_00001.png, ...
+}
+
+impl RecordFormat {
+    // Un .gif se graba como GIF; cualquier otra ruta es la carpeta de la secuencia de PNG
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("gif") => RecordFormat::Gif,
+            _ => RecordFormat::PngSequence,
+        }
+    }
+}
+
+// Ruta nueva dentro de captures/ con la hora actual, para no pisar capturas anteriores
+pub fn default_path(kind: &str, extension: &str) -> PathBuf {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let name = format!("{}-{}{:03}", kind, now.as_secs(), now.subsec_millis());
+    let path = Path::new(CAPTURE_DIR).join(name);
+    if extension.is_empty() { path } else { path.with_extension(extension) }
+}
+
+fn to_rgb(pixels: &[u32], width: usize, height: usize) -> RgbImage {
+    RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let pixel = pixels[y as usize * width + x as usize];
+        image::Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+    })
+}
+
+fn create_parent(path: &Path) -> Result<(), CaptureError> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent).map_err(|e| CaptureError::io(parent, e)),
+        _ => Ok(()),
+    }
+}
+
+fn save_png(image: &RgbImage, path: &Path) -> Result<(), CaptureError> {
+    image.save_with_format(path, image::ImageFormat::Png).map_err(|e| match e {
+        image::ImageError::IoError(source) => CaptureError::io(path, source),
+        other => CaptureError::encode(path, other),
+    })
+}
+
+// Guarda el cuadro como PNG en captures/ y devuelve la ruta
+pub fn screenshot(framebuffer: &Framebuffer) -> Result<PathBuf, CaptureError> {
+    let path = default_path("screenshot", "png");
+    create_parent(&path)?;
+    save_png(&to_rgb(&framebuffer.buffer, framebuffer.width, framebuffer.height), &path)?;
+    Ok(path)
+}
+
+// Cuadro copiado tal cual para el hilo que codifica, con el tiempo que estuvo en pantalla.
+// La conversión a RGB se hace en ese hilo para no cargarle el trabajo al juego
+struct PendingFrame {
+    pixels: Vec<u32>,
+    width: usize,
+    height: usize,
+    delay_ms: u32,
+}
+
+impl PendingFrame {
+    fn image(&self) -> RgbImage {
+        to_rgb(&self.pixels, self.width, self.height)
+    }
+}
+
+// Graba cuadros en un hilo aparte para no frenar el juego. Si el codificador se atrasa se
+// descartan cuadros nuevos y el anterior queda más tiempo, así se conserva la velocidad real
+pub struct Recorder {
+    path: PathBuf,
+    sender: Option<SyncSender<PendingFrame>>,
+    worker: Option<JoinHandle<Result<usize, CaptureError>>>,
+    last_frame: Option<Instant>,
+    last_delay_ms: u32,
+    pending: Option<PendingFrame>, // Cuadro que todavía no sabe cuánto dura
+}
+
+fn encode_gif(path: &Path, frames: Receiver<PendingFrame>) -> Result<usize, CaptureError> {
+    let file = File::create(path).map_err(|e| CaptureError::io(path, e))?;
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+    encoder.set_repeat(Repeat::Infinite).map_err(|e| CaptureError::encode(path, e))?;
+
+    // El GIF guarda la duración en centésimas: se arrastra el redondeo para no acelerar
+    let (mut total_ms, mut written_cs) = (0, 0);
+    let mut count = 0;
+    for frame in frames {
+        total_ms += frame.delay_ms;
+        let delay_cs = total_ms / 10 - written_cs;
+        written_cs += delay_cs;
+
+        let image = frame.image();
+        let (width, height) = (image.width() / GIF_DOWNSCALE, image.height() / GIF_DOWNSCALE);
+        let rgba: RgbaImage = image::DynamicImage::ImageRgb8(image).thumbnail_exact(width, height).into_rgba8();
+        let delay = Delay::from_numer_denom_ms(delay_cs * 10, 1);
+        encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay)).map_err(|e| CaptureError::encode(path, e))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn encode_png_sequence(path: &Path, frames: Receiver<PendingFrame>) -> Result<usize, CaptureError> {
+    let mut count = 0;
+    for frame in frames {
+        save_png(&frame.image(), &path.join(format!("frame_{:05}.png", count)))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+impl Recorder {
+    pub fn start(format: RecordFormat, path: PathBuf) -> Result<Self, CaptureError> {
+        match format {
+            RecordFormat::Gif => create_parent(&path)?,
+            RecordFormat::PngSequence => fs::create_dir_all(&path).map_err(|e| CaptureError::io(&path, e))?,
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+        let worker_path = path.clone();
+        let worker = thread::spawn(move || match format {
+            RecordFormat::Gif => encode_gif(&worker_path, receiver),
+            RecordFormat::PngSequence => encode_png_sequence(&worker_path, receiver),
+        });
+
+        Ok(Recorder { path, sender: Some(sender), worker: Some(worker), last_frame: None, last_delay_ms: 0, pending: None })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Agrega el cuadro al ritmo real del juego; si el codificador se atrasa, lo descarta
+    pub fn push(&mut self, framebuffer: &Framebuffer) {
+        let now = Instant::now();
+        let elapsed_ms = self.last_frame.map_or(0, |last| now.duration_since(last).as_millis() as u32);
+        self.last_frame = Some(now);
+        self.queue(framebuffer, elapsed_ms, false);
+    }
+
+    // Agrega el cuadro suponiendo que el anterior duró `elapsed_ms`. Espera al codificador
+    // en vez de descartar, para grabar sin depender del reloj
+    pub fn push_with_delay(&mut self, framebuffer: &Framebuffer, elapsed_ms: u32) {
+        self.queue(framebuffer, elapsed_ms, true);
+    }
+
+    // Cada cuadro se envía cuando llega el siguiente, que es cuando se sabe cuánto duró
+    fn queue(&mut self, framebuffer: &Framebuffer, elapsed_ms: u32, wait: bool) {
+        self.last_delay_ms = elapsed_ms;
+        if let (Some(mut frame), Some(sender)) = (self.pending.take(), &self.sender) {
+            frame.delay_ms += elapsed_ms;
+            let sent = if wait {
+                sender.send(frame).map_err(|e| TrySendError::Disconnected(e.0))
+            } else {
+                sender.try_send(frame)
+            };
+            if let Err(TrySendError::Full(frame)) = sent {
+                self.pending = Some(frame);
+                return;
+            }
+        }
+        self.pending = Some(PendingFrame {
+            pixels: framebuffer.buffer.clone(),
+            width: framebuffer.width,
+            height: framebuffer.height,
+            delay_ms: 0,
+        });
+    }
+
+    // Termina de escribir y devuelve la ruta y la cantidad de cuadros grabados. El último
+    // cuadro dura lo mismo que el anterior
+    pub fn finish(mut self) -> Result<(PathBuf, usize), CaptureError> {
+        if let (Some(mut frame), Some(sender)) = (self.pending.take(), &self.sender) {
+            frame.delay_ms += self.last_delay_ms;
+            let _ = sender.send(frame);
+        }
+        self.sender = None;
+
+        let count = match self.worker.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result?,
+            _ => return Err(CaptureError::encode(&self.path, "encoder thread panicked")),
+        };
+        Ok((self.path.clone(), count))
+    }
+}
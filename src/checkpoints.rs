@@ -0,0 +1,205 @@
+use nalgebra::Vector2;
+use once_cell::sync::Lazy;
+use crate::assets::{self, TextureHandle};
+use crate::doors::Doors;
+use crate::enemies::Enemies;
+use crate::items::{Inventory, Items};
+use crate::player::Player;
+use crate::sprites::{Anchor, Sprite, SpriteShape};
+use crate::textures::Texture;
+use crate::weapons::Weapons;
+
+const REACH_RADIUS: f32 = 0.5; // Distancia a la que se activa un checkpoint
+const FLAG_SHAPE: SpriteShape = SpriteShape { height: 0.6, width_scale: 1.0, anchor: Anchor::Floor };
+
+// Banderín en un mástil: gris hasta que se activa, rojo después
+fn flag(name: &str, color: u32) -> TextureHandle {
+    assets::insert_texture(name, Texture::from_fn(32, 48, move |x, y| {
+        let pennant = x >= 7 && (4..22).contains(&y) && (x - 7) * 9 < (9 - (y as i32 - 13).unsigned_abs()) * 21;
+        if (4..7).contains(&x) {
+            0xFF4A3018
+        } else if pennant {
+            0xFF000000 | color
+        } else {
+            0x00000000
+        }
+    }))
+}
+static FLAG: Lazy<TextureHandle> = Lazy::new(|| flag("checkpoint", 0xB0A890));
+static FLAG_REACHED: Lazy<TextureHandle> = Lazy::new(|| flag("checkpoint_reached", 0xE8303C));
+
+// 'c' es un checkpoint en una celda vacía
+pub fn is_checkpoint(cell: char) -> bool {
+    cell == 'c'
+}
+
+// Lo que se guarda al pasar por un checkpoint para volver ahí después de morir
+pub struct Save {
+    pub pos: Vector2<f32>,
+    pub angle: f32,
+    pub inventory: Inventory,
+    pub items: Items,     // Los objetos que quedaban sin recoger
+    pub enemies: Enemies, // Los enemigos que seguían vivos, donde estaban
+    pub doors: Doors,     // Cuánto estaba abierta cada puerta
+    pub weapons: Weapons, // El arma elegida y las balas que quedaban
+}
+
+pub struct Checkpoints {
+    list: Vec<Vector2<f32>>,
+    reached: Option<usize>,
+    save: Option<Save>,
+}
+
+// Carga las texturas de las banderas al iniciar, así el reporte de assets las incluye
+pub fn preload() {
+    for texture in [&FLAG, &FLAG_REACHED] {
+        Lazy::force(texture);
+    }
+}
+
+impl Checkpoints {
+    pub fn from_maze(maze: &[Vec<char>]) -> Self {
+        let mut list = Vec::new();
+        for (y, line) in maze.iter().enumerate() {
+            for (x, &cell) in line.iter().enumerate() {
+                if is_checkpoint(cell) {
+                    list.push(Vector2::new(x as f32 + 0.5, y as f32 + 0.5));
+                }
+            }
+        }
+        Checkpoints { list, reached: None, save: None }
+    }
+
+    // Vuelve a leer los checkpoints de un mapa editado. Lo guardado se conserva, y el
+    // último alcanzado sigue marcado si todavía está en el mapa
+    pub fn reload(&mut self, maze: &[Vec<char>]) {
+        let mut fresh = Checkpoints::from_maze(maze);
+        if let Some(reached) = self.reached {
+            fresh.reached = fresh.list.iter().position(|&pos| pos == self.list[reached]);
+        }
+        fresh.save = self.save.take();
+        *self = fresh;
+    }
+
+    // Guarda el estado al llegar a un checkpoint distinto del último; devuelve si pasó
+    pub fn update(&mut self, player: &Player, items: &Items, enemies: &Enemies, doors: &Doors, weapons: &Weapons) -> bool {
+        let Some(index) = self.list.iter().position(|pos| (pos - player.pos).norm() < REACH_RADIUS) else {
+            return false;
+        };
+        if self.reached == Some(index) {
+            return false;
+        }
+        self.reached = Some(index);
+        self.save = Some(Save {
+            pos: self.list[index],
+            angle: player.a,
+            inventory: player.inventory.clone(),
+            items: items.clone(),
+            enemies: enemies.clone(),
+            doors: doors.clone(),
+            weapons: weapons.clone(),
+        });
+        true
+    }
+
+    // El último checkpoint alcanzado, si hubo alguno
+    pub fn save(&self) -> Option<&Save> {
+        self.save.as_ref()
+    }
+
+    pub fn sprites(&self) -> impl Iterator<Item = Sprite> + '_ {
+        self.list.iter().enumerate().map(|(i, &pos)| {
+            let texture = if self.reached == Some(i) { FLAG_REACHED.get() } else { FLAG.get() };
+            Sprite { pos, texture, shape: FLAG_SHAPE, phase: 0.0 }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemies::{self, Enemies};
+    use crate::items::ItemKind;
+    use crate::test_level;
+    use crate::world::World;
+    use crate::weapons::{Projectiles, WeaponKind};
+
+    // Un checkpoint al principio del pasillo, una moneda y una puerta más adelante
+    const CORRIDOR: [&str; 3] = ["+--------+", "|c  $ D  |", "+--------+"];
+
+    struct Game {
+        level: World,
+        items: Items,
+        enemies: Enemies,
+        weapons: Weapons,
+    }
+
+    fn game() -> Game {
+        let mut level = test_level::level(&CORRIDOR);
+        let items = Items::from_maze(&mut level.maze);
+        let enemies = Enemies { list: vec![enemies::tests::enemy(Vector2::new(7.5, 1.5), 0.0)] };
+        Game { level, items, enemies, weapons: Weapons::new() }
+    }
+
+    #[test]
+    fn nothing_saved_before_a_checkpoint() {
+        let Game { level, items, enemies, weapons } = game();
+        let mut checkpoints = Checkpoints::from_maze(&level.maze);
+        let player = Player::new(Vector2::new(1.5 + REACH_RADIUS + 0.1, 1.5), 0.0);
+        assert!(!checkpoints.update(&player, &items, &enemies, &level.doors, &weapons));
+        assert!(checkpoints.save().is_none());
+    }
+
+    #[test]
+    fn activates_within_reach_once() {
+        let Game { level, items, enemies, weapons } = game();
+        let mut checkpoints = Checkpoints::from_maze(&level.maze);
+        let player = Player::new(Vector2::new(1.5 + REACH_RADIUS - 0.1, 1.5), 0.0);
+        assert!(checkpoints.update(&player, &items, &enemies, &level.doors, &weapons));
+        assert!(checkpoints.save().is_some());
+        // Quedarse encima no lo vuelve a guardar
+        assert!(!checkpoints.update(&player, &items, &enemies, &level.doors, &weapons));
+    }
+
+    #[test]
+    fn save_keeps_the_state_when_reached() {
+        let Game { mut level, mut items, mut enemies, mut weapons } = game();
+        let mut checkpoints = Checkpoints::from_maze(&level.maze);
+
+        // Puerta a medio abrir, una moneda en el bolsillo y un disparo gastado antes de llegar
+        let mut player = Player::new(Vector2::new(5.5, 1.5), 0.0);
+        level.doors.interact(&player);
+        level.doors.update(0.2);
+        player.inventory.add(ItemKind::Coin);
+        let mut projectiles = Projectiles::new();
+        weapons.select(WeaponKind::Charge);
+        assert!(weapons.fire(&player, &level, &mut projectiles));
+        player.pos = Vector2::new(1.7, 1.4);
+        player.a = 1.0;
+        assert!(checkpoints.update(&player, &items, &enemies, &level.doors, &weapons));
+
+        // Lo que pasa después no cambia lo guardado
+        level.doors.update(1.0);
+        player.pos = Vector2::new(4.5, 1.5);
+        items.collect(&mut player);
+        enemies.list[0].pos.x -= 1.0;
+        weapons.refill();
+
+        let Some(save) = checkpoints.save() else {
+            panic!("the checkpoint was not saved");
+        };
+        // Se vuelve al centro de la celda del checkpoint, no a donde estaba el jugador
+        assert_eq!(save.pos, Vector2::new(1.5, 1.5));
+        assert_eq!(save.angle, 1.0);
+        assert_eq!(save.inventory.coins, 1);
+        assert_eq!(save.items.list().len(), 1);
+        assert_eq!(save.enemies.list.len(), 1);
+        assert_eq!(save.enemies.list[0].pos, Vector2::new(7.5, 1.5));
+        assert!(save.weapons.current == WeaponKind::Charge);
+        assert_eq!(save.weapons.ammo(), WeaponKind::Charge.stats().max_ammo.map(|ammo| ammo - 1));
+        let Some(door) = save.doors.get(6, 1) else {
+            panic!("the door was not saved");
+        };
+        assert!((door.open - 0.3).abs() < 1e-4 && door.opening, "door saved at {}", door.open);
+    }
+}
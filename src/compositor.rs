@@ -0,0 +1,196 @@
+use crate::framebuffer::Framebuffer;
+use crate::textures::blend;
+
+// Capas en el orden en que se componen, de atrás hacia adelante
+#[derive(Clone, Copy)]
+pub enum Layer {
+    World,
+    Minimap,
+    Hud,
+    Overlay,
+}
+
+const LAYER_COUNT: usize = 4;
+// El cuadro final es opaco, así se puede seguir dibujando encima con blend_pixel
+const OPAQUE: u32 = 0xFF000000;
+
+// Un render target con sus opciones de composición. `dim` y `blur` se aplican a lo que
+// ya está compuesto debajo de la capa, así un menú puede oscurecer o desenfocar el juego
+pub struct RenderLayer {
+    pub target: Framebuffer,
+    pub opacity: f32,
+    pub visible: bool,
+    pub dim: f32,     // 0.0 deja el fondo igual, 1.0 lo vuelve negro
+    pub blur: usize,  // Radio del desenfoque de caja en pixeles, 0 sin desenfoque
+}
+
+pub struct Compositor {
+    layers: Vec<RenderLayer>,
+    output: Framebuffer,
+}
+
+fn channels(color: u32) -> [u32; 3] {
+    [(color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF]
+}
+
+// Promedio de la ventana. Multiplica por el recíproco en punto fijo porque dividir
+// tres canales por pixel domina el costo del desenfoque. Suma medio paso antes de
+// desplazar para redondear, si no un color parejo se oscurece un poco con cada pasada
+fn average(sums: &[u32; 3], count: u32) -> u32 {
+    let reciprocal = 65536 / count;
+    let channel = |sum: u32| (sum * reciprocal + 0x8000) >> 16;
+    OPAQUE | (channel(sums[0]) << 16) | (channel(sums[1]) << 8) | channel(sums[2])
+}
+
+fn accumulate(sums: &mut [u32; 3], color: u32, add: bool) {
+    for (sum, channel) in sums.iter_mut().zip(channels(color)) {
+        if add { *sum += channel } else { *sum -= channel }
+    }
+}
+
+// Desenfoque de caja separable con sumas móviles: primero cada fila y luego todas las
+// columnas a la vez, recorriendo la memoria por filas. La ventana se recorta en los bordes
+fn box_blur(framebuffer: &mut Framebuffer, radius: usize) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let buffer = &mut framebuffer.buffer;
+    let mut source = vec![0u32; width * height];
+
+    // Horizontal
+    source.copy_from_slice(buffer);
+    for y in 0..height {
+        let row = &source[y * width..(y + 1) * width];
+        let mut sums = [0u32; 3];
+        let mut count = 0;
+        for &pixel in row.iter().take(radius + 1) {
+            accumulate(&mut sums, pixel, true);
+            count += 1;
+        }
+        for x in 0..width {
+            buffer[y * width + x] = average(&sums, count);
+            if x + radius + 1 < width {
+                accumulate(&mut sums, row[x + radius + 1], true);
+                count += 1;
+            }
+            if x >= radius {
+                accumulate(&mut sums, row[x - radius], false);
+                count -= 1;
+            }
+        }
+    }
+
+    // Vertical, con una suma por columna
+    source.copy_from_slice(buffer);
+    let mut sums = vec![[0u32; 3]; width];
+    for y in 0..(radius + 1).min(height) {
+        for (x, sum) in sums.iter_mut().enumerate() {
+            accumulate(sum, source[y * width + x], true);
+        }
+    }
+    let mut count = (radius + 1).min(height) as u32;
+
+    for y in 0..height {
+        for (x, sum) in sums.iter().enumerate() {
+            buffer[y * width + x] = average(sum, count);
+        }
+        if y + radius + 1 < height {
+            let row = (y + radius + 1) * width;
+            for (x, sum) in sums.iter_mut().enumerate() {
+                accumulate(sum, source[row + x], true);
+            }
+            count += 1;
+        }
+        if y >= radius {
+            let row = (y - radius) * width;
+            for (x, sum) in sums.iter_mut().enumerate() {
+                accumulate(sum, source[row + x], false);
+            }
+            count -= 1;
+        }
+    }
+}
+
+impl Compositor {
+    pub fn new(width: usize, height: usize) -> Self {
+        let layers = (0..LAYER_COUNT)
+            .map(|_| RenderLayer {
+                target: Framebuffer::new(width, height),
+                opacity: 1.0,
+                visible: true,
+                dim: 0.0,
+                blur: 0,
+            })
+            .collect();
+
+        Compositor { layers, output: Framebuffer::new(width, height) }
+    }
+
+    pub fn layer(&mut self, layer: Layer) -> &mut RenderLayer {
+        &mut self.layers[layer as usize]
+    }
+
+    // Framebuffer donde se dibuja una capa
+    pub fn target(&mut self, layer: Layer) -> &mut Framebuffer {
+        &mut self.layers[layer as usize].target
+    }
+
+    // Limpia todas las capas. Las capas de arriba quedan transparentes
+    pub fn clear(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.target.clear();
+        }
+    }
+
+    // Compone las capas visibles en orden y devuelve el cuadro final, listo para el
+    // post-procesado y la ventana. El mundo es opaco; en las demás capas cuenta el alfa
+    // de cada pixel
+    pub fn composite(&mut self) -> &mut Framebuffer {
+        let (world, upper) = self.layers.split_first().expect("compositor has layers");
+        for (out, &src) in self.output.buffer.iter_mut().zip(world.target.buffer.iter()) {
+            *out = src | OPAQUE;
+        }
+
+        for layer in upper.iter().filter(|layer| layer.visible) {
+            if layer.blur > 0 {
+                box_blur(&mut self.output, layer.blur);
+            }
+            if layer.dim > 0.0 {
+                // Escala rojo y azul juntos y luego el verde, en enteros
+                let keep = ((1.0 - layer.dim.min(1.0)) * 256.0) as u32;
+                for pixel in self.output.buffer.iter_mut() {
+                    let red_blue = (((*pixel & 0xFF00FF) * keep) >> 8) & 0xFF00FF;
+                    let green = (((*pixel & 0x00FF00) * keep) >> 8) & 0x00FF00;
+                    *pixel = OPAQUE | red_blue | green;
+                }
+            }
+
+            // La mayoría de los pixeles de las capas de arriba están vacíos o son opacos
+            let opaque = layer.opacity >= 1.0;
+            for (out, &src) in self.output.buffer.iter_mut().zip(layer.target.buffer.iter()) {
+                match src >> 24 {
+                    0 => {}
+                    0xFF if opaque => *out = src,
+                    alpha => *out = OPAQUE | blend(*out, src, alpha as f32 / 255.0 * layer.opacity),
+                }
+            }
+        }
+
+        &mut self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blur_keeps_flat_colors() {
+        let mut framebuffer = Framebuffer::new(16, 8);
+        for color in [0xFFFFFFFF, 0xFF808080, 0xFF0A141E] {
+            framebuffer.buffer.fill(color);
+            for radius in 1..=4 {
+                box_blur(&mut framebuffer, radius);
+                assert!(framebuffer.buffer.iter().all(|&pixel| pixel == color), "{:08X} changed with radius {}", color, radius);
+            }
+        }
+    }
+}
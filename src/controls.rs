@@ -1,8 +1,56 @@
-use minifb::{Key, Window};
-use crate::player::Player;
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use crate::player::{Player, MAX_HEALTH};
 use crate::audio::AudioPlayer;
+use crate::doors::{self, Doors};
+use crate::walls::{WallMap, WallShape};
+use crate::heights::HeightMap;
+use crate::world::World;
+use nalgebra::Vector2;
 
-pub fn process_events(window: &Window, player: &mut Player, maze: &Vec<Vec<char>>, block_size: usize, steps_player: &AudioPlayer) {
+const MAX_STEP: f32 = 0.3; // Diferencia de altura máxima que se puede subir de un paso
+const MIN_CAMERA_HEIGHT: f32 = 0.1;
+const MAX_CAMERA_HEIGHT: f32 = 1.9;
+const PITCH_SPEED: f32 = 10.0; // Pixeles que se mueve el horizonte por cuadro
+const MAX_PITCH: f32 = 300.0;
+const BOB_SPEED: f32 = 0.25; // Avance de la fase del balanceo por cuadro caminando
+
+// Se puede avanzar a una celda vacía, a una puerta abierta,
+// o dentro de una celda con paneles delgados mientras no se cruce ninguno.
+// Los enemigos que patrullan usan la misma regla
+pub fn can_move(maze: &[Vec<char>], walls: &WallMap, heights: &HeightMap, doors: &Doors, from: &Vector2<f32>, to: &Vector2<f32>) -> bool {
+    let x = to.x as usize;
+    let y = to.y as usize;
+    if to.x < 0.0 || to.y < 0.0 || y >= maze.len() || x >= maze[y].len() {
+        return false;
+    }
+
+    // Los pisos demasiado altos bloquean el paso
+    if heights.floor(x, y) - heights.floor(from.x as usize, from.y as usize) > MAX_STEP {
+        return false;
+    }
+
+    if doors::is_door(maze[y][x]) {
+        return doors.is_passable(x, y);
+    }
+
+    !walls.blocks_move(from, to)
+}
+
+// Se puede estar parado en `pos` si cae dentro del mapa y no queda metido en un bloque
+// ni en una puerta cerrada
+pub fn can_stand(maze: &[Vec<char>], walls: &WallMap, doors: &Doors, pos: &Vector2<f32>) -> bool {
+    let x = pos.x as usize;
+    let y = pos.y as usize;
+    if pos.x < 0.0 || pos.y < 0.0 || y >= maze.len() || x >= maze[y].len() {
+        return false;
+    }
+    if doors::is_door(maze[y][x]) {
+        return doors.is_passable(x, y);
+    }
+    !matches!(walls.shape(x, y), WallShape::Block)
+}
+
+pub fn process_events(window: &Window, player: &mut Player, world: &mut World, steps_player: &AudioPlayer) {
     const MOVE_SPEED: f32 = 0.05;
     const ROTATION_SPEED: f32 = std::f32::consts::PI / 50.0;
     let mut moved = false;
@@ -30,14 +78,58 @@ pub fn process_events(window: &Window, player: &mut Player, maze: &Vec<Vec<char>
         moved = true;
     }
 
-    let next_cell_x = next_pos_x as usize;
-    let next_cell_y = next_pos_y as usize;
+    // Subir o bajar la cámara con RePág y AvPág
+    if window.is_key_down(Key::PageUp) {
+        player.camera_height = (player.camera_height + MOVE_SPEED).min(MAX_CAMERA_HEIGHT);
+    }
+    if window.is_key_down(Key::PageDown) {
+        player.camera_height = (player.camera_height - MOVE_SPEED).max(MIN_CAMERA_HEIGHT);
+    }
 
-    if next_cell_x < maze[0].len() && next_cell_y < maze.len() && maze[next_cell_y][next_cell_x] == ' ' {
+    // Mirar arriba y abajo con las flechas, o arrastrando con el botón derecho del mouse
+    if window.is_key_down(Key::Up) {
+        player.pitch += PITCH_SPEED;
+    }
+    if window.is_key_down(Key::Down) {
+        player.pitch -= PITCH_SPEED;
+    }
+    if window.get_mouse_down(MouseButton::Right) {
+        if let Some((_, mouse_y)) = window.get_mouse_pos(MouseMode::Clamp) {
+            let (_, height) = window.get_size();
+            player.pitch = (height as f32 / 2.0 - mouse_y) * 2.0 * MAX_PITCH / height as f32;
+        }
+    }
+    player.pitch = player.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+    // Activar o desactivar el balanceo al caminar con B
+    if window.is_key_pressed(Key::B, KeyRepeat::No) {
+        player.head_bob = !player.head_bob;
+    }
+
+    // Abrir o cerrar puertas con E
+    if window.is_key_pressed(Key::E, KeyRepeat::No) {
+        world.doors.interact(player);
+    }
+
+    // Usar un botiquín con Q si falta vida
+    if window.is_key_pressed(Key::Q, KeyRepeat::No) && player.health < MAX_HEALTH && player.inventory.health > 0 {
+        player.inventory.health -= 1;
+        player.heal(1);
+    }
+
+    let next_pos = Vector2::new(next_pos_x, next_pos_y);
+    let previous_pos = player.pos;
+
+    if can_move(&world.maze, &world.walls, &world.heights, &world.doors, &player.pos, &next_pos) {
         player.pos.x = next_pos_x;
         player.pos.y = next_pos_y;
     }
 
+    // El balanceo sigue la velocidad real, así que se apaga al chocar o detenerse
+    let speed = (player.pos - previous_pos).norm() / MOVE_SPEED;
+    player.bob_amount += (speed - player.bob_amount) * 0.2;
+    player.bob_phase += BOB_SPEED * speed;
+
     // Reproducir o pausar el sonido de los pasos dependiendo si el jugador se mueve o no
     if moved {
         steps_player.play();
@@ -45,26 +137,3 @@ pub fn process_events(window: &Window, player: &mut Player, maze: &Vec<Vec<char>
         steps_player.pause();
     }
 }
-
-// Función que comprueba si una posición está dentro de una pared o muy cerca de ella
-fn is_colliding(x: f32, y: f32, maze: &Vec<Vec<char>>, threshold: f32) -> bool {
-    let map_x = x.floor() as isize;
-    let map_y = y.floor() as isize;
-
-    if map_x < 0 || map_x >= maze[0].len() as isize || map_y < 0 || map_y >= maze.len() as isize {
-        return true;
-    }
-
-    let cell = maze[map_y as usize][map_x as usize];
-
-    if cell != ' ' {
-        let dist_x = x - map_x as f32;
-        let dist_y = y - map_y as f32;
-
-        if dist_x < threshold || dist_x > (1.0 - threshold) || dist_y < threshold || dist_y > (1.0 - threshold) {
-            return true;
-        }
-    }
-
-    false
-}
\ No newline at end of file
@@ -0,0 +1,142 @@
+use crate::player::Player;
+use crate::walls;
+
+const DOOR_SPEED: f32 = 1.5; // Fracción de la puerta que se desliza por segundo
+const INTERACT_DISTANCE: f32 = 1.0;
+pub const DOOR_OFFSET: f32 = 0.5; // Las puertas quedan hundidas a la mitad de la celda
+
+#[derive(Clone)]
+pub struct Door {
+    pub x: usize,
+    pub y: usize,
+    pub vertical: bool,     // Panel en un plano x = constante
+    pub open: f32,          // 0.0 cerrada, 1.0 abierta
+    pub opening: bool,
+    pub lock: Option<char>, // Llave necesaria para abrirla
+}
+
+#[derive(Clone)]
+pub struct Doors {
+    doors: Vec<Door>,
+}
+
+// 'D' es una puerta normal, 'R', 'B' y 'Y' están cerradas con llave
+pub fn is_door(cell: char) -> bool {
+    matches!(cell, 'D' | 'R' | 'B' | 'Y')
+}
+
+// Las llaves son la misma letra en minúscula
+pub fn is_key(cell: char) -> bool {
+    matches!(cell, 'r' | 'b' | 'y')
+}
+
+// Color asociado a cada llave y a su puerta
+pub fn key_color(cell: char) -> u32 {
+    match cell.to_ascii_lowercase() {
+        'r' => 0xFF0000,
+        'b' => 0x0000FF,
+        'y' => 0xFFFF00,
+        _ => 0xFFFFFF,
+    }
+}
+
+fn door_lock(cell: char) -> Option<char> {
+    match cell {
+        'R' | 'B' | 'Y' => Some(cell.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn is_solid(maze: &[Vec<char>], x: isize, y: isize) -> bool {
+    if x < 0 || y < 0 || y as usize >= maze.len() || x as usize >= maze[y as usize].len() {
+        return true;
+    }
+    !walls::is_empty(maze[y as usize][x as usize])
+}
+
+impl Doors {
+    pub fn from_maze(maze: &[Vec<char>]) -> Self {
+        let mut doors = Vec::new();
+
+        for (y, line) in maze.iter().enumerate() {
+            for (x, &cell) in line.iter().enumerate() {
+                if is_door(cell) {
+                    // Si hay pared arriba y abajo la puerta corta el paso este-oeste
+                    let vertical = is_solid(maze, x as isize, y as isize - 1)
+                        && is_solid(maze, x as isize, y as isize + 1);
+
+                    doors.push(Door {
+                        x,
+                        y,
+                        vertical,
+                        open: 0.0,
+                        opening: false,
+                        lock: door_lock(cell),
+                    });
+                }
+            }
+        }
+
+        Doors { doors }
+    }
+
+    // Vuelve a leer las puertas de un mapa editado; las que siguen en su lugar conservan
+    // cuánto estaban abiertas
+    pub fn reload(&mut self, maze: &[Vec<char>]) {
+        let mut fresh = Doors::from_maze(maze);
+        for door in &mut fresh.doors {
+            if let Some(old) = self.get(door.x, door.y) {
+                door.open = old.open;
+                door.opening = old.opening;
+            }
+        }
+        *self = fresh;
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Door> {
+        self.doors.iter().find(|door| door.x == x && door.y == y)
+    }
+
+    // Sólo se puede atravesar una puerta casi abierta del todo
+    pub fn is_passable(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).map_or(true, |door| door.open > 0.9)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for door in self.doors.iter_mut() {
+            if door.opening {
+                door.open = (door.open + DOOR_SPEED * dt).min(1.0);
+            } else {
+                door.open = (door.open - DOOR_SPEED * dt).max(0.0);
+            }
+        }
+    }
+
+    // Abre o cierra la puerta que está frente al jugador
+    pub fn interact(&mut self, player: &Player) {
+        let front_x = (player.pos.x + player.a.cos() * INTERACT_DISTANCE).floor() as usize;
+        let front_y = (player.pos.y + player.a.sin() * INTERACT_DISTANCE).floor() as usize;
+        let player_x = player.pos.x.floor() as usize;
+        let player_y = player.pos.y.floor() as usize;
+
+        let door = self
+            .doors
+            .iter_mut()
+            .find(|door| (door.x == front_x && door.y == front_y) || (door.x == player_x && door.y == player_y));
+
+        if let Some(door) = door {
+            if let Some(key) = door.lock {
+                if !player.inventory.has_key(key) {
+                    return;
+                }
+            }
+
+            // No cerrar la puerta encima del jugador
+            if door.opening && door.x == player_x && door.y == player_y {
+                return;
+            }
+
+            door.opening = !door.opening;
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+use std::sync::Arc;
+use nalgebra::Vector2;
+use once_cell::sync::Lazy;
+use crate::animation::{Animation, LoopMode};
+use crate::assets::{self, AnimationHandle};
+use crate::controls;
+use crate::player::Player;
+use crate::sprites::{Anchor, Sprite, SpriteShape};
+use crate::textures::{self, Texture};
+use crate::world::World;
+
+const ATTACK_DISTANCE: f32 = 2.0; // A esta distancia del jugador el enemigo ataca
+const PHASE_STEP: f32 = 0.37;     // Desfase entre enemigos para que no se muevan todos a la vez
+const WALK_SPEED: f32 = 1.2;      // Celdas por segundo al patrullar
+const TURN_SPEED: f32 = 3.0;      // Radianes por segundo al girar
+const WAYPOINT_RADIUS: f32 = 0.1; // Distancia a la que se da por alcanzado un punto de patrulla
+const ATTACK_REACH: f32 = 1.2;    // El golpe sólo alcanza al jugador si sigue así de cerca
+const ATTACK_HIT_TIME: f32 = 0.16; // Segundos del clip de ataque en que llega el golpe
+const CONTACT_RADIUS: f32 = 0.5;  // Tocar a un enemigo también hace daño
+const STUN_IMMUNITY: f32 = 0.6;   // Segundos después del clip de dolor en que otro golpe no lo vuelve a aturdir
+const CAGNEY_HEALTH: u32 = 6;
+const CAGNEY_SHAPE: SpriteShape = SpriteShape { height: 0.8, width_scale: 1.0, anchor: Anchor::Floor };
+const WALKER_HEALTH: u32 = 4;
+const WALKER_SHAPE: SpriteShape = SpriteShape { height: 0.7, width_scale: 1.0, anchor: Anchor::Floor };
+const WALKER_ROTATIONS: usize = 8;
+const WALKER_SIZE: (u32, u32) = (64, 96);
+
+// Las animaciones de cada clip están en assets/manifest.txt como `cagney_idle`, `cagney_walk`...
+static CAGNEY: Lazy<ClipSet> = Lazy::new(|| ClipSet::load("cagney"));
+// Los que patrullan son tazas dibujadas en código, con los 8 ángulos en cada clip, así se
+// ve hacia dónde caminan
+static WALKER: Lazy<ClipSet> = Lazy::new(ClipSet::walker);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Clip {
+    Idle,
+    Walk,
+    Attack,
+    Hurt,
+    Die,
+}
+
+impl Clip {
+    const ALL: [Clip; 5] = [Clip::Idle, Clip::Walk, Clip::Attack, Clip::Hurt, Clip::Die];
+
+    fn name(self) -> &'static str {
+        match self {
+            Clip::Idle => "idle",
+            Clip::Walk => "walk",
+            Clip::Attack => "attack",
+            Clip::Hurt => "hurt",
+            Clip::Die => "die",
+        }
+    }
+}
+
+// Una animación por clip, en el orden de Clip::ALL
+pub struct ClipSet {
+    clips: Vec<AnimationHandle>,
+}
+
+impl ClipSet {
+    fn load(name: &str) -> Self {
+        ClipSet { clips: Clip::ALL.iter().map(|clip| assets::animation(&format!("{}_{}", name, clip.name()))).collect() }
+    }
+
+    fn walker() -> Self {
+        ClipSet { clips: Clip::ALL.iter().map(|&clip| walker_clip(clip)).collect() }
+    }
+
+    fn get(&self, clip: Clip) -> AnimationHandle {
+        self.clips[clip as usize]
+    }
+}
+
+// Postura de un cuadro de la taza caminante
+#[derive(Clone, Copy)]
+struct Pose {
+    stride: f32,  // Fase del paso; los pies van en contrafase
+    step: f32,    // Cuánto levanta los pies, 0.0 con los dos en el piso
+    stretch: f32, // Escala vertical desde los pies: más de 1.0 se estira, menos se aplasta
+    hurt: f32,    // Cuánto se tiñe de rojo
+}
+
+fn in_ellipse(x: f32, y: f32, rx: f32, ry: f32) -> bool {
+    (x / rx).powi(2) + (y / ry).powi(2) <= 1.0
+}
+
+// Dibuja la taza vista desde `view_angle`, relativo a hacia dónde mira, con 0.0 de frente.
+// Una parte que apunta `angle` desde el frente queda corrida sin(view_angle - angle) hacia
+// la derecha de la pantalla y mira a la cámara si cos(view_angle - angle) es positivo
+fn walker_image(view_angle: f32, pose: Pose) -> Texture {
+    const OUTLINE: u32 = 0xFF1E1E1E;
+    const CUP: u32 = 0xFFF6EEDC;
+    const SHADE: u32 = 0xFFDCCFB6;
+    const RED: u32 = 0xFFD8303C;
+    let side = |angle: f32| ((view_angle - angle).sin(), (view_angle - angle).cos());
+    let (face_x, face_z) = side(0.0);
+    let (handle_x, handle_z) = side(FRAC_PI_2);
+    let eyes = [side(0.4), side(-0.4)];
+    let (width, height) = WALKER_SIZE;
+    let (center, bottom) = (width as f32 / 2.0, height as f32);
+
+    Texture::from_fn(width, height, move |x, y| {
+        // Coordenadas antes de estirar, con los pies fijos en el borde de abajo
+        let px = x as f32 + 0.5 - center;
+        let py = bottom - (bottom - (y as f32 + 0.5)) / pose.stretch;
+        let half_width = 17.0 - (py - 28.0) * 0.1;
+        let in_body = (28.0..78.0).contains(&py) && px.abs() <= half_width;
+        let handle_distance = ((px - handle_x * 19.0).powi(2) + (py - 52.0).powi(2)).sqrt();
+        let in_handle = (6.0..10.0).contains(&handle_distance);
+        let handle_color = if (7.0..=9.0).contains(&handle_distance) { CUP } else { OUTLINE };
+        // Popote inclinado hacia atrás
+        let in_straw = (6.0..28.0).contains(&py) && (px + face_x * 10.0 * (28.0 - py) / 22.0).abs() < 2.0;
+
+        let color = if in_handle && handle_z > 0.3 {
+            handle_color
+        } else if in_body && eyes.iter().any(|&(eye_x, eye_z)| eye_z > 0.2 && in_ellipse(px - eye_x * 12.0, py - 50.0, 2.5 * eye_z.max(0.4), 5.0)) {
+            OUTLINE
+        } else if in_body && face_z > 0.2 && in_ellipse(px - face_x * 14.0, py - 59.0, 2.5 * face_z.max(0.4), 2.0) {
+            RED
+        } else if in_straw {
+            if (py as i32 / 3) % 2 == 0 { RED } else { CUP }
+        } else if in_ellipse(px, py - 28.0, 17.0, 5.0) {
+            if in_ellipse(px, py - 28.0, 13.5, 3.0) { 0xFF5A3218 } else if in_ellipse(px, py - 28.0, 15.5, 4.0) { CUP } else { OUTLINE }
+        } else if in_body {
+            if half_width - px.abs() < 2.0 || py > 76.0 {
+                OUTLINE
+            } else if (34.0..40.0).contains(&py) {
+                RED
+            } else if px > half_width * 0.55 {
+                SHADE
+            } else {
+                CUP
+            }
+        } else if in_handle {
+            // El asa del otro lado queda detrás de la taza
+            handle_color
+        } else {
+            // Piernas y zapatos; los zapatos apuntan hacia donde mira
+            let foot = [-1.0f32, 1.0].into_iter().enumerate().find_map(|(i, leg)| {
+                let lift = pose.step * (pose.stride + i as f32 * PI).sin().max(0.0) * 5.0;
+                let leg_x = leg * 7.0;
+                if in_ellipse(px - leg_x - face_x * 4.0, py - (90.0 - lift), 5.0, 3.0) {
+                    Some(RED)
+                } else if (px - leg_x).abs() < 2.0 && (76.0..90.0 - lift).contains(&py) {
+                    Some(OUTLINE)
+                } else {
+                    None
+                }
+            });
+            match foot {
+                Some(color) => color,
+                None => return 0x00000000,
+            }
+        };
+        0xFF000000 | textures::blend(color, 0xFF2020, pose.hurt)
+    })
+}
+
+// Un clip de la taza caminante, con cada cuadro dibujado desde los 8 ángulos
+fn walker_clip(clip: Clip) -> AnimationHandle {
+    let still = Pose { stride: 0.0, step: 0.0, stretch: 1.0, hurt: 0.0 };
+    let (poses, frame_seconds, loop_mode) = match clip {
+        Clip::Idle => (vec![still, Pose { stretch: 0.96, ..still }], 0.4, LoopMode::PingPong),
+        Clip::Walk => ((0..4).map(|i| Pose { stride: i as f32 * FRAC_PI_2, step: 1.0, ..still }).collect(), 0.15, LoopMode::Loop),
+        // El golpe llega en el segundo cuadro, a ATTACK_HIT_TIME
+        Clip::Attack => (vec![Pose { stretch: 0.85, ..still }, Pose { stretch: 1.2, ..still }, still, still], 0.14, LoopMode::Once),
+        Clip::Hurt => (vec![Pose { stretch: 0.9, hurt: 0.7, ..still }, Pose { hurt: 0.35, ..still }], 0.06, LoopMode::Once),
+        Clip::Die => ((1..=4).map(|i| Pose { stretch: 1.0 - i as f32 * 0.2, hurt: 0.3, ..still }).collect(), 0.3, LoopMode::Once),
+    };
+    let images = poses
+        .iter()
+        .flat_map(|&pose| (0..WALKER_ROTATIONS).map(move |rotation| walker_image(rotation as f32 * TAU / WALKER_ROTATIONS as f32, pose)))
+        .collect();
+    assets::insert_animation(&format!("walker_{}", clip.name()), Animation::from_rotations(images, WALKER_ROTATIONS, frame_seconds, loop_mode))
+}
+
+// Estado de animación de una entidad: el clip actual y su propio reloj. El desfase sólo
+// se aplica a los clips que se repiten, los de una pasada siempre empiezan desde el principio
+#[derive(Clone)]
+pub struct Animator {
+    clip: Clip,
+    time: f32,  // Segundos desde que empezó el clip
+    phase: f32,
+}
+
+impl Animator {
+    pub fn new(phase: f32) -> Self {
+        Animator { clip: Clip::Idle, time: 0.0, phase }
+    }
+
+    pub fn clip(&self) -> Clip {
+        self.clip
+    }
+
+    // Cambia de clip y reinicia el reloj; volver a pedir el clip actual no lo reinicia
+    pub fn play(&mut self, clip: Clip) {
+        if self.clip != clip {
+            self.clip = clip;
+            self.time = 0.0;
+        }
+    }
+
+    // Vuelve a empezar el clip actual
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // Un clip de una pasada terminó cuando su reloj supera la duración; los demás nunca terminan
+    pub fn finished(&self, clips: &ClipSet) -> bool {
+        let animation = clips.get(self.clip).get();
+        animation.loop_mode == LoopMode::Once && self.time >= animation.duration()
+    }
+
+    // `view_angle` es desde dónde se mira, relativo a hacia dónde apunta la entidad
+    pub fn frame(&self, clips: &ClipSet, view_angle: f32) -> Arc<Texture> {
+        let animation = clips.get(self.clip).get();
+        let time = if animation.loop_mode == LoopMode::Once { self.time } else { self.time + self.phase };
+        animation.frame_facing(time, view_angle)
+    }
+}
+
+#[derive(Clone)]
+pub struct Enemy {
+    pub pos: Vector2<f32>,
+    pub facing: f32,              // Ángulo hacia donde mira, como el del jugador
+    pub animator: Animator,
+    patrol: Vec<Vector2<f32>>,    // Puntos que recorre en orden; vacío si se queda quieto
+    waypoint: usize,
+    struck: bool,                 // Si el ataque actual ya golpeó
+    stun_cooldown: f32,           // Segundos hasta que un golpe lo pueda volver a aturdir
+    health: u32,
+    clips: &'static ClipSet,
+    shape: SpriteShape,
+}
+
+// Diferencia entre dos ángulos llevada a [-PI, PI]
+fn angle_difference(a: f32, b: f32) -> f32 {
+    (a - b + PI).rem_euclid(TAU) - PI
+}
+
+impl Enemy {
+    pub fn new(pos: Vector2<f32>, facing: f32, phase: f32) -> Self {
+        Enemy { pos, facing, animator: Animator::new(phase), patrol: Vec::new(), waypoint: 0, struck: false, stun_cooldown: 0.0, health: CAGNEY_HEALTH, clips: &CAGNEY, shape: CAGNEY_SHAPE }
+    }
+
+    // Taza caminante que recorre `patrol`
+    pub fn walker(pos: Vector2<f32>, facing: f32, phase: f32, patrol: Vec<Vector2<f32>>) -> Self {
+        Enemy { patrol, health: WALKER_HEALTH, clips: &WALKER, shape: WALKER_SHAPE, ..Enemy::new(pos, facing, phase) }
+    }
+
+    // Sprite visto desde `viewer`, con la rotación según hacia dónde mira el enemigo
+    pub fn sprite(&self, viewer: Vector2<f32>) -> Sprite {
+        let to_viewer = viewer - self.pos;
+        let view_angle = to_viewer.y.atan2(to_viewer.x) - self.facing;
+        Sprite { pos: self.pos, texture: self.animator.frame(self.clips, view_angle), shape: self.shape, phase: 0.0 }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    // Recibe un disparo: se encoge con el clip de dolor, o muere si se quedó sin vida.
+    // Después de encogerse pasa un rato sin que los golpes lo aturdan, así el fuego
+    // continuo le sigue quitando vida pero no lo deja quieto para siempre
+    pub fn hit(&mut self, damage: u32) {
+        if self.is_dead() {
+            return;
+        }
+        self.health = self.health.saturating_sub(damage);
+        if self.is_dead() {
+            self.animator.play(Clip::Die);
+            self.animator.restart();
+        } else if self.stun_cooldown <= 0.0 {
+            self.animator.play(Clip::Hurt);
+            self.animator.restart();
+            self.stun_cooldown = self.clips.get(Clip::Hurt).get().duration() + STUN_IMMUNITY;
+        }
+    }
+
+    // Si un punto a altura `z` cae dentro del sprite: un cilindro del ancho del cuadro
+    // actual, parado sobre un piso a `floor`
+    pub fn contains(&self, point: Vector2<f32>, z: f32, floor: f32) -> bool {
+        let radius = self.shape.height * self.shape.aspect(&self.animator.frame(self.clips, 0.0)) / 2.0;
+        let (bottom, top) = self.shape.extent(floor, 0.0, 0.0);
+        (point - self.pos).norm() < radius && (bottom..top).contains(&z)
+    }
+
+    // Gira hacia `target` sin pasarse; devuelve cuánto le falta
+    fn turn_towards(&mut self, target: Vector2<f32>, dt: f32) -> f32 {
+        let direction = target - self.pos;
+        let difference = angle_difference(direction.y.atan2(direction.x), self.facing);
+        let step = difference.clamp(-TURN_SPEED * dt, TURN_SPEED * dt);
+        self.facing = angle_difference(self.facing + step, 0.0);
+        difference - step
+    }
+
+    // Camina al siguiente punto de la patrulla; primero gira y después avanza. Choca con
+    // paredes y puertas igual que el jugador; si algo le corta el paso sigue con el
+    // siguiente punto
+    fn walk_patrol(&mut self, dt: f32, world: &World) {
+        let target = self.patrol[self.waypoint];
+        let remaining = (target - self.pos).norm();
+        if remaining < WAYPOINT_RADIUS {
+            self.waypoint = (self.waypoint + 1) % self.patrol.len();
+            return;
+        }
+
+        if self.turn_towards(target, dt).abs() < FRAC_PI_4 {
+            let direction = Vector2::new(self.facing.cos(), self.facing.sin());
+            let next = self.pos + direction * (WALK_SPEED * dt).min(remaining);
+            if controls::can_move(&world.maze, &world.walls, &world.heights, &world.doors, &self.pos, &next) {
+                self.pos = next;
+            } else {
+                self.waypoint = (self.waypoint + 1) % self.patrol.len();
+            }
+        }
+    }
+
+    // Daño que recibe el jugador en este cuadro: por tocar al enemigo, o cuando el ataque
+    // llega al momento del golpe y el jugador no se alejó lo suficiente. También devuelve
+    // si el ataque acertó
+    fn damage(&mut self, player: &Player) -> (u32, bool) {
+        if self.is_dead() {
+            return (0, false);
+        }
+        let distance = (player.pos - self.pos).norm();
+        let contact = (distance < CONTACT_RADIUS) as u32;
+        let mut landed = false;
+        if self.animator.clip() == Clip::Attack && !self.struck && self.animator.time() >= ATTACK_HIT_TIME {
+            self.struck = true;
+            landed = distance < ATTACK_REACH;
+        }
+        (contact + landed as u32, landed)
+    }
+
+    // Elige el clip según lo que pasa en el juego: ataca si el jugador está cerca y termina
+    // cada ataque antes de decidir de nuevo; si no, patrulla o se queda quieto. Un golpe lo
+    // deja quieto hasta que termina el clip de dolor, y muerto ya no hace nada
+    fn think(&mut self, dt: f32, world: &World, player: &Player) {
+        let stunned = self.animator.clip() == Clip::Hurt && !self.animator.finished(self.clips);
+        if self.is_dead() || stunned {
+            return;
+        }
+
+        let attacking = self.animator.clip() == Clip::Attack;
+        if attacking && !self.animator.finished(self.clips) {
+            self.turn_towards(player.pos, dt);
+            return;
+        }
+
+        if (player.pos - self.pos).norm() < ATTACK_DISTANCE {
+            self.animator.play(Clip::Attack);
+            if attacking {
+                self.animator.restart();
+            }
+            self.struck = false;
+        } else if !self.patrol.is_empty() {
+            self.animator.play(Clip::Walk);
+            self.walk_patrol(dt, world);
+        } else {
+            self.animator.play(Clip::Idle);
+        }
+    }
+}
+
+// Posición, hacia dónde mira y puntos de patrulla
+type Spawn = (Vector2<f32>, f32, Vec<Vector2<f32>>);
+
+#[derive(Clone)]
+pub struct Enemies {
+    pub list: Vec<Enemy>,
+}
+
+// Carga las animaciones de todos los clips al iniciar, así el reporte de assets las incluye
+pub fn preload() {
+    Lazy::force(&CAGNEY);
+    Lazy::force(&WALKER);
+}
+
+impl Enemies {
+    pub fn spawn() -> Self {
+        let spawns: [Spawn; 5] = [
+            (Vector2::new(2.0, 5.0), -FRAC_PI_2, vec![]),
+            (Vector2::new(11.0, 3.5), PI, vec![]),
+            (Vector2::new(5.0, 5.0), PI, vec![]),
+            // Los que patrullan arrancan en el centro de una celda del pasillo (antes en
+            // (8, 7) y (7, 2), sobre el borde entre celdas) para caminar sin rozar paredes
+            (Vector2::new(8.0, 7.5), PI, vec![Vector2::new(3.5, 7.5), Vector2::new(8.0, 7.5)]),
+            (Vector2::new(7.5, 1.5), FRAC_PI_2, vec![Vector2::new(7.5, 3.5), Vector2::new(7.5, 1.5)]),
+        ];
+        let list = spawns
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pos, facing, patrol))| {
+                let phase = i as f32 * PHASE_STEP;
+                if patrol.is_empty() {
+                    Enemy::new(pos, facing, phase)
+                } else {
+                    Enemy::walker(pos, facing, phase, patrol)
+                }
+            })
+            .collect();
+        Enemies { list }
+    }
+
+    // Avanza a los enemigos y devuelve el daño que le hicieron al jugador junto con dónde
+    // están los que acertaron un ataque. Los muertos desaparecen cuando termina el clip de muerte
+    pub fn update(&mut self, dt: f32, world: &World, player: &Player) -> (u32, Vec<Vector2<f32>>) {
+        let mut damage = 0;
+        let mut strikes = Vec::new();
+        for enemy in &mut self.list {
+            enemy.animator.update(dt);
+            enemy.stun_cooldown = (enemy.stun_cooldown - dt).max(0.0);
+            enemy.think(dt, world, player);
+            let (amount, landed) = enemy.damage(player);
+            damage += amount;
+            if landed {
+                strikes.push(enemy.pos);
+            }
+        }
+        self.list.retain(|enemy| !(enemy.is_dead() && enemy.animator.finished(enemy.clips)));
+        (damage, strikes)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::test_level;
+    use crate::world::World;
+
+    const CLIP_FRAME: f32 = 0.1;
+    const ROOM: [&str; 3] = ["+---------+", "|         |", "+---------+"];
+
+    // Clips generados con duraciones conocidas: cada cuadro es un texel con su número
+    static CLIPS: Lazy<ClipSet> = Lazy::new(|| ClipSet {
+        clips: Clip::ALL
+            .iter()
+            .map(|&clip| {
+                let (count, loop_mode) = match clip {
+                    Clip::Idle | Clip::Walk => (2, LoopMode::Loop),
+                    Clip::Attack => (5, LoopMode::Once),
+                    Clip::Hurt => (2, LoopMode::Once),
+                    Clip::Die => (3, LoopMode::Once),
+                };
+                let frames = (0..count).map(|i| Texture::from_fn(1, 1, move |_, _| i)).collect();
+                assets::insert_animation(&format!("test_{}", clip.name()), Animation::from_frames(frames, CLIP_FRAME, loop_mode))
+            })
+            .collect(),
+    });
+
+    // Enemigo quieto con los clips de prueba
+    pub fn enemy(pos: Vector2<f32>, phase: f32) -> Enemy {
+        Enemy { pos, facing: 0.0, animator: Animator::new(phase), patrol: Vec::new(), waypoint: 0, struck: false, stun_cooldown: 0.0, health: CAGNEY_HEALTH, clips: &CLIPS, shape: CAGNEY_SHAPE }
+    }
+
+    fn frame_number(animator: &Animator) -> u32 {
+        animator.frame(&CLIPS, 0.0).get_pixel_color(0, 0)
+    }
+
+    fn update(enemies: &mut Enemies, level: &World, dt: f32, player: &Player) -> (u32, Vec<Vector2<f32>>) {
+        enemies.update(dt, level, player)
+    }
+
+    #[test]
+    fn attacks_within_reach_and_finishes_the_attack() {
+        let level = test_level::level(&ROOM);
+        let mut enemies = Enemies { list: vec![enemy(Vector2::new(1.5, 1.5), 0.0)] };
+        let mut player = Player::new(Vector2::new(6.5, 1.5), 0.0);
+        update(&mut enemies, &level, 0.05, &player);
+        assert!(enemies.list[0].animator.clip() == Clip::Idle);
+
+        player.pos.x = 1.5 + ATTACK_DISTANCE - 0.1;
+        update(&mut enemies, &level, 0.05, &player);
+        assert!(enemies.list[0].animator.clip() == Clip::Attack);
+
+        // Aunque el jugador se aleje, el ataque sigue hasta el final de su clip
+        player.pos.x = 6.5;
+        update(&mut enemies, &level, 0.3, &player);
+        assert!(enemies.list[0].animator.clip() == Clip::Attack);
+        update(&mut enemies, &level, 0.3, &player);
+        assert!(enemies.list[0].animator.clip() == Clip::Idle);
+    }
+
+    #[test]
+    fn attack_starts_over_while_player_stays_close() {
+        let level = test_level::level(&ROOM);
+        let mut enemies = Enemies { list: vec![enemy(Vector2::new(1.5, 1.5), 0.0)] };
+        let player = Player::new(Vector2::new(2.5, 1.5), 0.0);
+        update(&mut enemies, &level, 0.05, &player);
+        update(&mut enemies, &level, 0.6, &player);
+        let animator = &enemies.list[0].animator;
+        assert!(animator.clip() == Clip::Attack);
+        assert_eq!(animator.time(), 0.0);
+    }
+
+    #[test]
+    fn patrol_turns_back_at_walls() {
+        let level = test_level::level(&["+-------+", "|   |   |", "+-------+"]);
+        let patrol = vec![Vector2::new(6.5, 1.5), Vector2::new(1.5, 1.5)];
+        let mut enemies = Enemies { list: vec![Enemy { patrol, ..enemy(Vector2::new(1.5, 1.5), 0.0) }] };
+        let player = Player::new(Vector2::new(50.0, 50.0), 0.0);
+
+        let mut farthest: f32 = 0.0;
+        for _ in 0..60 {
+            update(&mut enemies, &level, 0.05, &player);
+            farthest = farthest.max(enemies.list[0].pos.x);
+        }
+        assert!(farthest > 3.5 && farthest < 4.0, "walked up to x = {}", farthest);
+        // Al chocar siguió con el siguiente punto de la patrulla
+        assert_eq!(enemies.list[0].waypoint, 1);
+    }
+
+    #[test]
+    fn patrolling_enemies_turn_with_their_walk() {
+        let enemies = Enemies::spawn();
+        for enemy in enemies.list.iter().filter(|enemy| !enemy.patrol.is_empty()) {
+            let mut walker = enemy.clone();
+            walker.animator.play(Clip::Walk);
+            // De frente y de espaldas se ven imágenes distintas
+            let front = walker.animator.frame(walker.clips, 0.0);
+            let back = walker.animator.frame(walker.clips, PI);
+            assert!(!Arc::ptr_eq(&front, &back));
+        }
+    }
+
+    #[test]
+    fn walker_face_only_shows_from_the_front() {
+        let still = Pose { stride: 0.0, step: 0.0, stretch: 1.0, hurt: 0.0 };
+        // Un ojo, en la columna 27 y la fila 50, queda sobre el cuerpo blanco de la taza
+        let eye = |view_angle: f32| walker_image(view_angle, still).get_pixel_color(27, 50);
+        assert_eq!(eye(0.0), 0xFF1E1E1E);
+        assert_eq!(eye(PI), 0xFFF6EEDC);
+    }
+
+    #[test]
+    fn spawned_enemies_get_staggered_phases() {
+        let enemies = Enemies::spawn();
+        for (i, enemy) in enemies.list.iter().enumerate() {
+            assert!((enemy.animator.phase - i as f32 * PHASE_STEP).abs() < 1e-6, "enemy {} has phase {}", i, enemy.animator.phase);
+        }
+    }
+
+    #[test]
+    fn phase_offsets_only_looping_clips() {
+        let mut animator = Animator::new(CLIP_FRAME * 1.5);
+        assert_eq!(frame_number(&Animator::new(0.0)), 0);
+        assert_eq!(frame_number(&animator), 1);
+
+        // Los clips de una pasada empiezan siempre desde su primer cuadro
+        animator.play(Clip::Attack);
+        assert_eq!(frame_number(&animator), 0);
+    }
+
+    #[test]
+    fn contains_points_inside_the_sprite() {
+        // Los cuadros de prueba son cuadrados, así que el radio es la mitad del alto
+        let enemy = enemy(Vector2::new(2.5, 1.5), 0.0);
+        let radius = CAGNEY_SHAPE.height / 2.0;
+        assert!(enemy.contains(Vector2::new(2.5, 1.5), 0.4, 0.0));
+        assert!(enemy.contains(Vector2::new(2.5 + radius * 0.9, 1.5), 0.4, 0.0));
+        assert!(!enemy.contains(Vector2::new(2.5 + radius * 1.1, 1.5), 0.4, 0.0));
+        // Por encima de la cabeza, o con el enemigo sobre un piso más alto
+        assert!(!enemy.contains(Vector2::new(2.5, 1.5), CAGNEY_SHAPE.height + 0.1, 0.0));
+        assert!(!enemy.contains(Vector2::new(2.5, 1.5), 0.4, 0.5));
+    }
+
+    #[test]
+    fn steady_fire_does_not_stun_lock() {
+        let level = test_level::level(&ROOM);
+        let mut enemies = Enemies { list: vec![enemy(Vector2::new(1.5, 1.5), 0.0)] };
+        let player = Player::new(Vector2::new(2.5, 1.5), 0.0);
+
+        // Un golpe cada 0.15 s, menos de lo que dura el clip de dolor
+        let mut attacked = false;
+        for step in 0..15 {
+            if step % 3 == 0 {
+                enemies.list[0].hit(1);
+            }
+            update(&mut enemies, &level, 0.05, &player);
+            attacked |= enemies.list[0].animator.clip() == Clip::Attack;
+        }
+        assert!(attacked, "the enemy never got to attack");
+        assert_eq!(enemies.list[0].health, CAGNEY_HEALTH - 5);
+    }
+}
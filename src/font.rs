@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use rusttype::{point, Font, Scale};
+use crate::assets::{self, AssetError};
+use crate::framebuffer::Framebuffer;
+
+// Copia de la fuente dentro del ejecutable, por si falta o no se puede leer la del manifiesto
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/font.ttf");
+
+static TEXT: Lazy<Mutex<GlyphCache>> = Lazy::new(|| Mutex::new(GlyphCache::new()));
+
+// Glifo ya rasterizado: cobertura de 0.0 a 1.0 por pixel, con su posición
+// relativa al punto de la línea base donde se dibuja
+struct Glyph {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    advance: f32,
+    coverage: Vec<f32>,
+}
+
+// La fuente se lee una sola vez y cada glifo se rasteriza una vez por tamaño
+struct GlyphCache {
+    font: Font<'static>,
+    glyphs: HashMap<(char, u32), Glyph>,
+}
+
+fn load_font() -> Font<'static> {
+    let bytes = assets::font("main").bytes();
+    if !bytes.is_empty() {
+        match Font::try_from_vec(bytes.to_vec()) {
+            Some(font) => return font,
+            None => assets::record(AssetError::decode("assets/font.ttf", "invalid TrueType data")),
+        }
+    }
+    Font::try_from_bytes(EMBEDDED_FONT).expect("embedded font is valid")
+}
+
+impl GlyphCache {
+    fn new() -> Self {
+        GlyphCache { font: load_font(), glyphs: HashMap::new() }
+    }
+
+    fn glyph(&mut self, c: char, size: f32) -> &Glyph {
+        let font = &self.font;
+        self.glyphs.entry((c, size.to_bits())).or_insert_with(|| {
+            let scaled = font.glyph(c).scaled(Scale::uniform(size));
+            let advance = scaled.h_metrics().advance_width;
+            let positioned = scaled.positioned(point(0.0, 0.0));
+
+            match positioned.pixel_bounding_box() {
+                Some(bb) => {
+                    let width = bb.width() as usize;
+                    let height = bb.height() as usize;
+                    let mut coverage = vec![0.0; width * height];
+                    positioned.draw(|x, y, v| coverage[y as usize * width + x as usize] = v);
+                    Glyph { left: bb.min.x, top: bb.min.y, width, height, advance, coverage }
+                }
+                // Espacios y otros caracteres sin pixeles sólo avanzan
+                None => Glyph { left: 0, top: 0, width: 0, height: 0, advance, coverage: Vec::new() },
+            }
+        })
+    }
+
+    fn kerning(&self, previous: Option<char>, c: char, size: f32) -> f32 {
+        previous.map_or(0.0, |p| self.font.pair_kerning(Scale::uniform(size), p, c))
+    }
+
+    fn width(&mut self, text: &str, size: f32) -> f32 {
+        let mut previous = None;
+        let mut width = 0.0;
+        for c in text.chars() {
+            width += self.kerning(previous, c, size) + self.glyph(c, size).advance;
+            previous = Some(c);
+        }
+        width
+    }
+
+    fn line_height(&self, size: f32) -> f32 {
+        let v_metrics = self.font.v_metrics(Scale::uniform(size));
+        v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Cómo se dibuja un texto: tamaño, color, alineación y efectos opcionales
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: u32,
+    pub align: Align,
+    pub shadow: Option<(i32, i32, u32)>, // Desplazamiento en x, en y y color de la sombra
+    pub outline: Option<(i32, u32)>,     // Grosor en pixeles y color del contorno
+}
+
+impl TextStyle {
+    pub fn new(size: f32, color: u32) -> Self {
+        TextStyle { size, color, align: Align::Left, shadow: None, outline: None }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn shadow(mut self, dx: i32, dy: i32, color: u32) -> Self {
+        self.shadow = Some((dx, dy, color));
+        self
+    }
+
+    pub fn outline(mut self, thickness: i32, color: u32) -> Self {
+        self.outline = Some((thickness, color));
+        self
+    }
+}
+
+// Parte el texto en líneas: respeta los saltos de línea y, con `max_width`, pasa a la
+// siguiente línea las palabras que no caben. Una palabra más ancha que la caja se corta
+fn wrap(cache: &mut GlyphCache, text: &str, size: f32, max_width: Option<f32>) -> Vec<String> {
+    let Some(max_width) = max_width else {
+        return text.lines().map(str::to_string).collect();
+    };
+
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if cache.width(&candidate, size) <= max_width {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for c in word.chars() {
+                line.push(c);
+                if cache.width(&line, size) > max_width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+
+        lines.push(line);
+    }
+    lines
+}
+
+// Dibuja una línea con la pluma empezando en `x` sobre la línea base `baseline`
+fn draw_line(cache: &mut GlyphCache, framebuffer: &mut Framebuffer, text: &str, x: f32, baseline: f32, size: f32, color: u32) {
+    let mut pen_x = x;
+    let mut previous = None;
+
+    for c in text.chars() {
+        pen_x += cache.kerning(previous, c, size);
+        previous = Some(c);
+
+        let glyph = cache.glyph(c, size);
+        let origin_x = pen_x.round() as i32 + glyph.left;
+        let origin_y = baseline.round() as i32 + glyph.top;
+
+        // La cobertura del glifo hace de alfa; blend_pixel recorta lo que cae fuera
+        for gy in 0..glyph.height {
+            let py = origin_y + gy as i32;
+            for gx in 0..glyph.width {
+                let px = origin_x + gx as i32;
+                let coverage = glyph.coverage[gy * glyph.width + gx];
+                if px >= 0 && py >= 0 && coverage > 0.0 {
+                    framebuffer.blend_pixel(px as usize, py as usize, color, coverage);
+                }
+            }
+        }
+
+        pen_x += glyph.advance;
+    }
+}
+
+// Dibuja las líneas alineadas dentro de una caja que empieza en `left` y mide `width`.
+// Primero la sombra, luego el contorno y al final el relleno
+fn draw_lines(cache: &mut GlyphCache, framebuffer: &mut Framebuffer, lines: &[String], left: f32, top: f32, width: f32, style: &TextStyle) {
+    let ascent = cache.font.v_metrics(Scale::uniform(style.size)).ascent;
+    let line_height = cache.line_height(style.size);
+
+    let mut passes = Vec::new();
+    if let Some((dx, dy, color)) = style.shadow {
+        passes.push((dx, dy, color));
+    }
+    if let Some((thickness, color)) = style.outline {
+        for dy in -thickness..=thickness {
+            for dx in -thickness..=thickness {
+                if (dx != 0 || dy != 0) && dx * dx + dy * dy <= thickness * thickness {
+                    passes.push((dx, dy, color));
+                }
+            }
+        }
+    }
+    passes.push((0, 0, style.color));
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = cache.width(line, style.size);
+        let x = match style.align {
+            Align::Left => left,
+            Align::Center => left + (width - line_width) / 2.0,
+            Align::Right => left + width - line_width,
+        };
+        let baseline = top + ascent + i as f32 * line_height;
+
+        for &(dx, dy, color) in &passes {
+            draw_line(cache, framebuffer, line, x + dx as f32, baseline + dy as f32, style.size, color);
+        }
+    }
+}
+
+// Ancho y alto en pixeles que ocupa el texto a este tamaño, para centrarlo o alinearlo.
+// Con varias líneas el ancho es el de la más larga
+pub fn measure(text: &str, size: f32) -> (usize, usize) {
+    let mut cache = TEXT.lock().unwrap_or_else(|e| e.into_inner());
+    let lines = wrap(&mut cache, text, size, None);
+    let width = lines.iter().map(|line| cache.width(line, size)).fold(0.0, f32::max);
+    let height = cache.line_height(size) * lines.len().max(1) as f32;
+    (width.ceil() as usize, height.ceil() as usize)
+}
+
+// Dibuja el texto anclado en `x` según la alineación: borde izquierdo, centro o borde
+// derecho. Acepta varias líneas separadas por '\n'
+pub fn draw_text_styled(framebuffer: &mut Framebuffer, text: &str, x: usize, y: usize, style: &TextStyle) {
+    let mut cache = TEXT.lock().unwrap_or_else(|e| e.into_inner());
+    let lines = wrap(&mut cache, text, style.size, None);
+    let width = framebuffer.width as f32;
+    let left = match style.align {
+        Align::Left => x as f32,
+        Align::Center => x as f32 - width / 2.0,
+        Align::Right => x as f32 - width,
+    };
+    draw_lines(&mut cache, framebuffer, &lines, left, y as f32, width, style);
+}
+
+// Dibuja el texto dentro de una caja de ancho `width`, partiendo las líneas que no caben
+// y alineándolas dentro de la caja. Devuelve el alto que ocupó
+pub fn draw_text_box(framebuffer: &mut Framebuffer, text: &str, x: usize, y: usize, width: usize, style: &TextStyle) -> usize {
+    let mut cache = TEXT.lock().unwrap_or_else(|e| e.into_inner());
+    let lines = wrap(&mut cache, text, style.size, Some(width as f32));
+    draw_lines(&mut cache, framebuffer, &lines, x as f32, y as f32, width as f32, style);
+    (cache.line_height(style.size) * lines.len() as f32).ceil() as usize
+}
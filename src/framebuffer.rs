@@ -1,3 +1,5 @@
+use crate::textures::Texture;
+
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
@@ -6,6 +8,27 @@ pub struct Framebuffer {
     current_color: u32,
 }
 
+// Los pixeles guardan alfa en el byte alto: lo que se dibuja es opaco y lo que queda del
+// fondo por defecto (0x000000) es transparente, así el framebuffer sirve como capa
+const OPAQUE: u32 = 0xFF000000;
+
+// Pone `color` encima del pixel `dst` con opacidad `t`, teniendo en cuenta el alfa de
+// `dst`. Sobre un pixel opaco es una mezcla normal de los dos colores
+fn over(dst: u32, color: u32, t: f32) -> u32 {
+    let dst_alpha = (dst >> 24) as f32 / 255.0;
+    let alpha = t + dst_alpha * (1.0 - t);
+    if alpha <= 0.0 {
+        return dst;
+    }
+
+    let channel = |shift: u32| {
+        let cd = ((dst >> shift) & 0xFF) as f32;
+        let cc = ((color >> shift) & 0xFF) as f32;
+        (((cc * t + cd * dst_alpha * (1.0 - t)) / alpha) as u32) << shift
+    };
+    (((alpha * 255.0) as u32) << 24) | channel(16) | channel(8) | channel(0)
+}
+
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
         Framebuffer {
@@ -25,7 +48,7 @@ impl Framebuffer {
 
     pub fn point(&mut self, x: usize, y: usize) {
         if x < self.width && y < self.height {
-            self.buffer[y * self.width + x] = self.current_color;
+            self.buffer[y * self.width + x] = self.current_color | OPAQUE;
         }
     }
 
@@ -36,4 +59,162 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    // Escribe un pixel con un color propio, sin cambiar el color actual
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x] = color | OPAQUE;
+        }
+    }
+
+    // Mezcla un color sobre lo que ya hay en el pixel, alpha = 1.0 lo reemplaza
+    pub fn blend_pixel(&mut self, x: usize, y: usize, color: u32, alpha: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            self.buffer[index] = over(self.buffer[index], color, alpha.clamp(0.0, 1.0));
+        }
+    }
+
+    // Punto con coordenadas que pueden caer fuera de la pantalla
+    fn plot(&mut self, x: isize, y: isize) {
+        if x >= 0 && y >= 0 {
+            self.point(x as usize, y as usize);
+        }
+    }
+
+    // Línea de Bresenham
+    pub fn line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+
+        loop {
+            self.plot(x, y);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    pub fn rect(&mut self, x: isize, y: isize, width: usize, height: usize) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let right = x + width as isize - 1;
+        let bottom = y + height as isize - 1;
+        self.line(x, y, right, y);
+        self.line(x, bottom, right, bottom);
+        self.line(x, y, x, bottom);
+        self.line(right, y, right, bottom);
+    }
+
+    pub fn fill_rect(&mut self, x: isize, y: isize, width: usize, height: usize) {
+        let x_start = (x.max(0) as usize).min(self.width);
+        let y_start = y.max(0) as usize;
+        let x_end = ((x + width as isize).max(0) as usize).min(self.width);
+        let y_end = ((y + height as isize).max(0) as usize).min(self.height);
+
+        // Rectángulo completamente fuera de la pantalla por la izquierda o la derecha
+        if x_start >= x_end {
+            return;
+        }
+
+        for row in y_start..y_end {
+            self.buffer[row * self.width + x_start..row * self.width + x_end].fill(self.current_color | OPAQUE);
+        }
+    }
+
+    // Circunferencia con el algoritmo del punto medio
+    pub fn circle(&mut self, cx: isize, cy: isize, radius: isize) {
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+
+        while x >= y {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                self.plot(cx + px, cy + py);
+            }
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, cx: isize, cy: isize, radius: isize) {
+        for dy in -radius..=radius {
+            let half = ((radius * radius - dy * dy) as f32).sqrt() as isize;
+            self.fill_rect(cx - half, cy + dy, (2 * half + 1) as usize, 1);
+        }
+    }
+
+    pub fn triangle(&mut self, a: (isize, isize), b: (isize, isize), c: (isize, isize)) {
+        self.line(a.0, a.1, b.0, b.1);
+        self.line(b.0, b.1, c.0, c.1);
+        self.line(c.0, c.1, a.0, a.1);
+    }
+
+    // Rellena los pixeles cuyo centro queda dentro del triángulo, recorriendo su caja
+    pub fn fill_triangle(&mut self, a: (isize, isize), b: (isize, isize), c: (isize, isize)) {
+        let edge = |p: (isize, isize), q: (isize, isize), x: isize, y: isize| (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0);
+        let area = edge(a, b, c.0, c.1);
+        if area == 0 {
+            return;
+        }
+
+        let x_min = a.0.min(b.0).min(c.0).max(0);
+        let x_max = a.0.max(b.0).max(c.0).min(self.width as isize - 1);
+        let y_min = a.1.min(b.1).min(c.1).max(0);
+        let y_max = a.1.max(b.1).max(c.1).min(self.height as isize - 1);
+
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                let w0 = edge(b, c, x, y);
+                let w1 = edge(c, a, x, y);
+                let w2 = edge(a, b, x, y);
+                // Mismo signo que el área sin importar el orden de los vértices
+                if (w0 * area >= 0) && (w1 * area >= 0) && (w2 * area >= 0) {
+                    self.point(x as usize, y as usize);
+                }
+            }
+        }
+    }
+
+    // Copia una textura escalada al rectángulo indicado, recortando lo que cae fuera de
+    // la pantalla. El alfa de cada texel se multiplica por `alpha`
+    pub fn blit(&mut self, texture: &Texture, x: isize, y: isize, width: usize, height: usize, alpha: f32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let x_start = x.max(0);
+        let y_start = y.max(0);
+        let x_end = (x + width as isize).min(self.width as isize);
+        let y_end = (y + height as isize).min(self.height as isize);
+
+        for py in y_start..y_end {
+            let ty = ((py - y) as usize * texture.height as usize / height) as u32;
+            for px in x_start..x_end {
+                let tx = ((px - x) as usize * texture.width as usize / width) as u32;
+                let color = texture.get_pixel_color(tx, ty);
+                let texel_alpha = (color >> 24) as f32 / 255.0 * alpha;
+                if texel_alpha > 0.0 {
+                    self.blend_pixel(px as usize, py as usize, color, texel_alpha);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use std::fs;
+use crate::player::Player;
+use crate::walls;
+
+const WALL_HEIGHT: f32 = 1.0;
+const HEIGHT_STEP: f32 = 0.25; // Cada dígito del archivo vale un cuarto de unidad
+
+// Alturas por celda. En las paredes es la altura de la pared y en las celdas
+// vacías la altura del piso, así se pueden tener paredes bajas, torres y plataformas.
+pub struct HeightMap {
+    walls: Vec<Vec<f32>>,
+    floors: Vec<Vec<f32>>,
+    pub max_wall: f32,
+}
+
+impl HeightMap {
+    // El archivo tiene la misma forma que maze.txt y es opcional: cada dígito
+    // reemplaza la altura por defecto de su celda, cualquier otro carácter la deja igual
+    pub fn load(filename: &str, maze: &[Vec<char>]) -> HeightMap {
+        let text = fs::read_to_string(filename).unwrap_or_default();
+        let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+
+        let mut walls = Vec::with_capacity(maze.len());
+        let mut floors = Vec::with_capacity(maze.len());
+
+        for (y, line) in maze.iter().enumerate() {
+            let mut wall_row = Vec::with_capacity(line.len());
+            let mut floor_row = Vec::with_capacity(line.len());
+
+            for (x, &cell) in line.iter().enumerate() {
+                let digit = lines
+                    .get(y)
+                    .and_then(|l| l.get(x))
+                    .and_then(|c| c.to_digit(10))
+                    .map(|d| d as f32 * HEIGHT_STEP);
+
+                if walls::is_empty(cell) {
+                    wall_row.push(0.0);
+                    floor_row.push(digit.unwrap_or(0.0));
+                } else {
+                    wall_row.push(digit.unwrap_or(WALL_HEIGHT));
+                    floor_row.push(0.0);
+                }
+            }
+
+            walls.push(wall_row);
+            floors.push(floor_row);
+        }
+
+        let max_wall = walls.iter().flatten().copied().fold(WALL_HEIGHT, f32::max);
+
+        HeightMap { walls, floors, max_wall }
+    }
+
+    pub fn wall(&self, x: usize, y: usize) -> f32 {
+        self.walls.get(y).and_then(|line| line.get(x)).copied().unwrap_or(WALL_HEIGHT)
+    }
+
+    pub fn floor(&self, x: usize, y: usize) -> f32 {
+        self.floors.get(y).and_then(|line| line.get(x)).copied().unwrap_or(0.0)
+    }
+
+    // Altura de la cámara: el piso bajo el jugador más la altura de sus ojos
+    pub fn camera_z(&self, player: &Player) -> f32 {
+        self.floor(player.pos.x as usize, player.pos.y as usize) + player.camera_height
+    }
+}
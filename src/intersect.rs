@@ -0,0 +1,35 @@
+use nalgebra::Vector2;
+
+// Intersección de un rayo con un panel delgado alineado a los ejes dentro de una celda.
+// `vertical` indica si el panel es un plano x = constante (true) o y = constante (false),
+// `offset` es la posición del panel dentro de la celda (0.0..1.0).
+// Devuelve la distancia a lo largo del rayo y la coordenada sobre el panel (0.0..1.0).
+pub fn intersect_panel(
+    origin: &Vector2<f32>,
+    dir: &Vector2<f32>,
+    map_x: isize,
+    map_y: isize,
+    vertical: bool,
+    offset: f32,
+) -> Option<(f32, f32)> {
+    let (distance, along, cell) = if vertical {
+        if dir.x == 0.0 {
+            return None;
+        }
+        let distance = (map_x as f32 + offset - origin.x) / dir.x;
+        (distance, origin.y + distance * dir.y, map_y)
+    } else {
+        if dir.y == 0.0 {
+            return None;
+        }
+        let distance = (map_y as f32 + offset - origin.y) / dir.y;
+        (distance, origin.x + distance * dir.x, map_x)
+    };
+
+    // El punto de impacto tiene que caer dentro de la celda actual
+    if distance < 0.0 || along.floor() as isize != cell {
+        return None;
+    }
+
+    Some((distance, along - cell as f32))
+}
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use nalgebra::Vector2;
+use once_cell::sync::Lazy;
+use crate::assets::{self, TextureHandle};
+use crate::doors::{self, key_color};
+use crate::player::Player;
+use crate::sprites::{Anchor, Sprite, SpriteShape};
+use crate::textures::{self, Texture};
+
+const PICKUP_RADIUS: f32 = 0.45; // Distancia a la que el jugador recoge un objeto
+
+// Una llave por color, en el orden de KEY_CELLS
+const KEY_CELLS: [char; 3] = ['r', 'b', 'y'];
+static KEYS: Lazy<[TextureHandle; 3]> = Lazy::new(|| {
+    KEY_CELLS.map(|cell| {
+        let color = key_color(cell);
+        assets::insert_texture(&format!("key_{}", cell), Texture::from_fn(48, 24, move |x, y| {
+            let (dx, dy) = (x as i32 - 10, y as i32 - 12);
+            let ring = dx * dx + dy * dy;
+            let shaft = (16..46).contains(&x) && (10..14).contains(&y);
+            let teeth = ((34..38).contains(&x) || (40..44).contains(&x)) && (14..20).contains(&y);
+            if (16..=64).contains(&ring) || shaft || teeth {
+                0xFF000000 | color
+            } else if (65..=81).contains(&ring) {
+                0xFF000000 | textures::blend(color, 0x000000, 0.5)
+            } else {
+                0x00000000
+            }
+        }))
+    })
+});
+// Moneda dorada con borde oscuro y una raya brillante
+static COIN: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("coin", Texture::from_fn(32, 32, |x, y| {
+    let (dx, dy) = (x as i32 - 16, y as i32 - 16);
+    let r = dx * dx + dy * dy;
+    if r < 100 && (13..19).contains(&x) && (8..24).contains(&y) {
+        0xFFFFF3A0
+    } else if r < 169 {
+        0xFFF0B428
+    } else if r < 225 {
+        0xFF9A6A10
+    } else {
+        0x00000000
+    }
+})));
+// Corazón: el interior de la curva (x² + y² - 1)³ - x²y³ < 0
+static HEART: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("heart", Texture::from_fn(32, 32, |x, y| {
+    let u = (x as f32 - 15.5) / 12.0;
+    let v = (17.0 - y as f32) / 12.0;
+    let inside = |u: f32, v: f32| (u * u + v * v - 1.0).powi(3) - u * u * v * v * v < 0.0;
+    if inside(u * 1.15, v * 1.15 - 0.05) {
+        0xFFE8303C
+    } else if inside(u, v) {
+        0xFF701018
+    } else {
+        0x00000000
+    }
+})));
+// Estrella de cinco puntas
+static STAR: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("star", Texture::from_fn(32, 32, |x, y| {
+    let (dx, dy) = (x as f32 - 15.5, y as f32 - 16.5);
+    let angle = dx.atan2(-dy);
+    let points = (angle * 2.5).cos().abs();
+    let radius = (dx * dx + dy * dy).sqrt();
+    let edge = 7.0 + 8.5 * points.powi(4);
+    if radius < edge - 2.0 {
+        0xFF60D8FF
+    } else if radius < edge {
+        0xFF1C5A9A
+    } else {
+        0x00000000
+    }
+})));
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Coin,
+    Key(char), // Abre la puerta de su color
+    Health,
+    PowerUp,
+}
+
+// '$' moneda, 'h' vida, '*' potenciador, y las llaves 'r', 'b', 'y'
+pub fn is_item(cell: char) -> bool {
+    matches!(cell, '$' | 'h' | '*') || doors::is_key(cell)
+}
+
+impl ItemKind {
+    fn from_cell(cell: char) -> Option<Self> {
+        match cell {
+            '$' => Some(ItemKind::Coin),
+            'h' => Some(ItemKind::Health),
+            '*' => Some(ItemKind::PowerUp),
+            _ if doors::is_key(cell) => Some(ItemKind::Key(cell)),
+            _ => None,
+        }
+    }
+
+    pub fn texture(self) -> Arc<Texture> {
+        match self {
+            ItemKind::Coin => COIN.get(),
+            ItemKind::Health => HEART.get(),
+            ItemKind::PowerUp => STAR.get(),
+            ItemKind::Key(cell) => KEYS[KEY_CELLS.iter().position(|&key| key == cell).unwrap_or(0)].get(),
+        }
+    }
+
+    // Color del punto que lo marca en el mapa
+    pub fn color(self) -> u32 {
+        match self {
+            ItemKind::Coin => 0xF0B428,
+            ItemKind::Health => 0xE8303C,
+            ItemKind::PowerUp => 0x60D8FF,
+            ItemKind::Key(cell) => key_color(cell),
+        }
+    }
+
+    fn shape(self) -> SpriteShape {
+        let height = match self {
+            ItemKind::Key(_) => 0.16,
+            ItemKind::Coin => 0.2,
+            ItemKind::Health | ItemKind::PowerUp => 0.25,
+        };
+        SpriteShape { height, width_scale: 1.0, anchor: Anchor::Floating { height: 0.2, bob: 0.05 } }
+    }
+
+    // Puntos que suma al recogerlo
+    fn score(self) -> u32 {
+        match self {
+            ItemKind::Coin => 10,
+            ItemKind::Key(_) => 25,
+            ItemKind::Health => 5,
+            ItemKind::PowerUp => 50,
+        }
+    }
+}
+
+// Lo que el jugador lleva encima
+#[derive(Clone, Default)]
+pub struct Inventory {
+    pub coins: u32,
+    pub keys: Vec<char>,
+    pub health: u32,    // Botiquines guardados
+    pub power_ups: u32,
+    pub score: u32,
+}
+
+impl Inventory {
+    pub fn add(&mut self, kind: ItemKind) {
+        match kind {
+            ItemKind::Coin => self.coins += 1,
+            ItemKind::Key(key) => self.keys.push(key),
+            ItemKind::Health => self.health += 1,
+            ItemKind::PowerUp => self.power_ups += 1,
+        }
+        self.score += kind.score();
+    }
+
+    pub fn has_key(&self, key: char) -> bool {
+        self.keys.contains(&key)
+    }
+}
+
+#[derive(Clone)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub pos: Vector2<f32>,
+}
+
+#[derive(Clone)]
+pub struct Items {
+    list: Vec<Item>,
+    taken: Vec<Vector2<f32>>, // Dónde estaban los objetos ya recogidos
+}
+
+// Carga las texturas de los objetos al iniciar, así el reporte de assets las incluye
+pub fn preload() {
+    for texture in [&COIN, &HEART, &STAR] {
+        Lazy::force(texture);
+    }
+    Lazy::force(&KEYS);
+}
+
+impl Items {
+    // Saca los objetos del mapa y deja sus celdas vacías
+    pub fn from_maze(maze: &mut [Vec<char>]) -> Self {
+        let mut list = Vec::new();
+        for (y, line) in maze.iter_mut().enumerate() {
+            for (x, cell) in line.iter_mut().enumerate() {
+                if let Some(kind) = ItemKind::from_cell(*cell) {
+                    list.push(Item { kind, pos: Vector2::new(x as f32 + 0.5, y as f32 + 0.5) });
+                    *cell = ' ';
+                }
+            }
+        }
+        Items { list, taken: Vec::new() }
+    }
+
+    // Vuelve a leer los objetos de un mapa editado sin reponer los que ya se recogieron
+    pub fn reload(&mut self, maze: &mut [Vec<char>]) {
+        let fresh = Items::from_maze(maze);
+        self.list = fresh.list.into_iter().filter(|item| !self.taken.contains(&item.pos)).collect();
+    }
+
+    pub fn list(&self) -> &[Item] {
+        &self.list
+    }
+
+    pub fn sprites(&self) -> impl Iterator<Item = Sprite> + '_ {
+        self.list.iter().map(|item| Sprite {
+            pos: item.pos,
+            texture: item.kind.texture(),
+            shape: item.kind.shape(),
+            phase: item.pos.x + item.pos.y, // Cada objeto flota a su ritmo
+        })
+    }
+
+    // Guarda en el inventario los objetos al alcance del jugador y los devuelve
+    pub fn collect(&mut self, player: &mut Player) -> Vec<Item> {
+        let (collected, remaining) = self.list.drain(..).partition(|item| (item.pos - player.pos).norm() < PICKUP_RADIUS);
+        self.list = remaining;
+        for item in &collected {
+            player.inventory.add(item.kind);
+            self.taken.push(item.pos);
+        }
+        collected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze;
+
+    #[test]
+    fn inventory_counts_and_scores_each_kind() {
+        let mut inventory = Inventory::default();
+        for kind in [ItemKind::Coin, ItemKind::Coin, ItemKind::Key('r'), ItemKind::Health, ItemKind::PowerUp] {
+            inventory.add(kind);
+        }
+        assert_eq!(inventory.coins, 2);
+        assert_eq!(inventory.health, 1);
+        assert_eq!(inventory.power_ups, 1);
+        assert!(inventory.has_key('r'));
+        assert!(!inventory.has_key('b'));
+        let score = 2 * ItemKind::Coin.score() + ItemKind::Key('r').score() + ItemKind::Health.score() + ItemKind::PowerUp.score();
+        assert_eq!(inventory.score, score);
+    }
+
+    #[test]
+    fn collects_only_items_in_reach() {
+        let mut maze = maze::from_rows(&["+----+", "|$ h |", "+----+"]);
+        let mut items = Items::from_maze(&mut maze);
+        assert_eq!(maze[1].iter().collect::<String>(), "|    |");
+        assert_eq!(items.list().len(), 2);
+
+        let mut player = Player::new(Vector2::new(1.6, 1.5), 0.0);
+        let collected = items.collect(&mut player);
+        assert_eq!(collected.len(), 1);
+        assert!(collected[0].kind == ItemKind::Coin);
+        assert_eq!(items.list().len(), 1);
+        assert_eq!(player.inventory.coins, 1);
+        assert!(items.collect(&mut player).is_empty());
+    }
+
+    #[test]
+    fn reload_skips_collected_items() {
+        let mut maze = maze::from_rows(&["+----+", "|$ h |", "+----+"]);
+        let mut items = Items::from_maze(&mut maze.clone());
+        items.collect(&mut Player::new(Vector2::new(1.5, 1.5), 0.0));
+
+        maze[1][4] = '*';
+        items.reload(&mut maze);
+        let kinds: Vec<_> = items.list().iter().map(|item| item.kind).collect();
+        assert!(kinds == [ItemKind::Health, ItemKind::PowerUp]);
+    }
+}
@@ -0,0 +1,176 @@
+use nalgebra::Vector2;
+use crate::player::Player;
+use crate::walls;
+
+const AMBIENT: f32 = 0.55;          // Luz mínima de cualquier celda
+const LAMP_INTENSITY: f32 = 1.0;
+const LAMP_RADIUS: f32 = 5.0;
+const TORCH_INTENSITY: f32 = 0.8;
+const TORCH_RADIUS: f32 = 3.0;
+const OCCLUSION_STEP: f32 = 0.1;    // Paso al recorrer el segmento entre la luz y la celda
+const MAX_LIGHT: f32 = 1.6;
+
+// Luz dinámica. Las que tienen duración se apagan poco a poco (destellos)
+pub struct PointLight {
+    pub pos: Vector2<f32>,
+    pub radius: f32,
+    pub intensity: f32,
+    pub duration: f32,
+    pub remaining: f32,
+}
+
+pub struct Lighting {
+    lightmap: Vec<Vec<f32>>, // Luz horneada en el centro de cada celda
+    pub lights: Vec<PointLight>,
+    pub torch: bool,         // Antorcha que sigue al jugador
+    torch_pos: Vector2<f32>,
+}
+
+// 'L' es una lámpara colgada en una celda vacía
+pub fn is_light(cell: char) -> bool {
+    cell == 'L'
+}
+
+// Las paredes opacas bloquean la luz, las rejas y el vidrio la dejan pasar
+fn blocks_light(cell: char) -> bool {
+    !walls::is_empty(cell) && !walls::is_see_through(cell)
+}
+
+fn falloff(distance: f32, radius: f32, intensity: f32) -> f32 {
+    if distance >= radius {
+        return 0.0;
+    }
+    let t = 1.0 - distance / radius;
+    intensity * t * t
+}
+
+// Recorre el segmento en la cuadrícula y comprueba que ninguna pared lo corte.
+// Las celdas de los extremos no cuentan, así las paredes quedan iluminadas por su cara
+fn is_visible(maze: &[Vec<char>], from: &Vector2<f32>, to: &Vector2<f32>) -> bool {
+    let delta = to - from;
+    let steps = (delta.norm() / OCCLUSION_STEP) as usize;
+    let from_cell = (from.x as usize, from.y as usize);
+    let to_cell = (to.x as usize, to.y as usize);
+
+    (1..steps).all(|i| {
+        let p = from + delta * (i as f32 / steps as f32);
+        let cell = (p.x as usize, p.y as usize);
+        if cell == from_cell || cell == to_cell {
+            return true;
+        }
+        maze.get(cell.1)
+            .and_then(|line| line.get(cell.0))
+            .map_or(true, |&c| !blocks_light(c))
+    })
+}
+
+// Multiplica un color ARGB por un nivel de luz, conservando el alfa
+pub fn shade(color: u32, light: f32) -> u32 {
+    let channel = |shift: u32| (((color >> shift) & 0xFF) as f32 * light).min(255.0) as u32;
+    (color & 0xFF000000) | (channel(16) << 16) | (channel(8) << 8) | channel(0)
+}
+
+impl Lighting {
+    // Calcula la luz de cada celda a partir de las lámparas del mapa
+    pub fn bake(maze: &[Vec<char>]) -> Self {
+        let lamps: Vec<Vector2<f32>> = maze
+            .iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.iter()
+                    .enumerate()
+                    .filter(|(_, &cell)| is_light(cell))
+                    .map(move |(x, _)| Vector2::new(x as f32 + 0.5, y as f32 + 0.5))
+            })
+            .collect();
+
+        let lightmap = maze
+            .iter()
+            .enumerate()
+            .map(|(y, line)| {
+                (0..line.len())
+                    .map(|x| {
+                        let center = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                        let lit: f32 = lamps
+                            .iter()
+                            .filter(|lamp| is_visible(maze, lamp, &center))
+                            .map(|lamp| falloff((center - lamp).norm(), LAMP_RADIUS, LAMP_INTENSITY))
+                            .sum();
+                        (AMBIENT + lit).min(MAX_LIGHT)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Lighting {
+            lightmap,
+            lights: Vec::new(),
+            torch: false,
+            torch_pos: Vector2::new(0.0, 0.0),
+        }
+    }
+
+    fn baked(&self, x: isize, y: isize) -> f32 {
+        if x < 0 || y < 0 {
+            return AMBIENT;
+        }
+        self.lightmap
+            .get(y as usize)
+            .and_then(|line| line.get(x as usize))
+            .copied()
+            .unwrap_or(AMBIENT)
+    }
+
+    // Luz horneada interpolada entre los centros de las celdas vecinas
+    fn sample_lightmap(&self, x: f32, y: f32) -> f32 {
+        let fx = x - 0.5;
+        let fy = y - 0.5;
+        let x0 = fx.floor() as isize;
+        let y0 = fy.floor() as isize;
+        let tx = fx - x0 as f32;
+        let ty = fy - y0 as f32;
+
+        let top = self.baked(x0, y0) * (1.0 - tx) + self.baked(x0 + 1, y0) * tx;
+        let bottom = self.baked(x0, y0 + 1) * (1.0 - tx) + self.baked(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    // Nivel de luz en un punto del mapa: la luz horneada más las luces dinámicas
+    pub fn light_at(&self, x: f32, y: f32) -> f32 {
+        let pos = Vector2::new(x, y);
+        let mut light = self.sample_lightmap(x, y);
+
+        if self.torch {
+            light += falloff((pos - self.torch_pos).norm(), TORCH_RADIUS, TORCH_INTENSITY);
+        }
+
+        for point in &self.lights {
+            let fade = if point.duration > 0.0 { point.remaining / point.duration } else { 1.0 };
+            light += falloff((pos - point.pos).norm(), point.radius, point.intensity * fade);
+        }
+
+        light.min(MAX_LIGHT)
+    }
+
+    // Destello breve, por ejemplo al recoger algo o en un ataque
+    pub fn flash(&mut self, pos: Vector2<f32>, intensity: f32, duration: f32) {
+        self.lights.push(PointLight {
+            pos,
+            radius: 3.0,
+            intensity,
+            duration,
+            remaining: duration,
+        });
+    }
+
+    pub fn update(&mut self, dt: f32, player: &Player) {
+        self.torch_pos = player.pos;
+
+        for point in self.lights.iter_mut() {
+            if point.duration > 0.0 {
+                point.remaining -= dt;
+            }
+        }
+        self.lights.retain(|point| point.duration <= 0.0 || point.remaining > 0.0);
+    }
+}
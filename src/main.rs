@@ -4,60 +4,223 @@ mod raycasting;
 mod controls;
 mod textures;
 mod audio;  
-
-use player::Player;
-use raycasting::cast_ray;
-use controls::process_events;
-use minifb::{Key, Window, WindowOptions};
+mod doors;
+mod intersect;
+mod walls;
+mod heights;
+mod lighting;
+mod benchmark;
+mod assets;
+mod font;
+mod framebuffer;
+mod animation;
+mod compositor;
+mod postfx;
+mod capture;
+mod replay;
+mod enemies;
+mod sprites;
+mod camera;
+mod items;
+mod checkpoints;
+mod weapons;
+mod world;
+#[cfg(test)]
+mod test_level;
+
+use player::{Player, MAX_HEALTH};
+use doors::{key_color, Doors};
+use walls::{WallMap, WallShape};
+use heights::HeightMap;
+use lighting::{shade, Lighting};
+use raycasting::{blocks_view, cast_ray, RayHit};
+use controls::{can_stand, process_events};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use nalgebra as na;
-use textures::Texture;
+use textures::{blend, Texture};
+use assets::{AnimationHandle, TextureHandle};
+use animation::{Animation, LoopMode};
+use font::{Align, TextStyle};
+use framebuffer::Framebuffer;
+use compositor::{Compositor, Layer};
+use postfx::PostFx;
+use capture::{RecordFormat, Recorder};
+use replay::{ReplayFrame, ReplayWriter};
+use enemies::Enemies;
+use items::{Inventory, ItemKind, Items};
+use checkpoints::Checkpoints;
+use weapons::{Projectiles, WeaponKind, Weapons};
+use sprites::{Anchor, Sprite, SpriteShape};
+use camera::{Camera, MAX_FOV, MIN_FOV};
+use world::World;
 use once_cell::sync::Lazy;
 use std::sync::Arc;
 use audio::AudioPlayer;
 use std::time::{Duration, Instant}; 
-use rusttype::{Font, Scale};
-use image::{DynamicImage, GenericImageView};
 
 const WIDTH: usize = 1040;
 const HEIGHT: usize = 800;
+const MAX_LIGHT_DISTANCE: f32 = 100.0; // Más lejos de esto el piso sólo recibe luz ambiente
+const STATUS_DURATION: Duration = Duration::from_secs(2); // Tiempo que se muestra un mensaje de estado
+const ASSET_POLL_INTERVAL: Duration = Duration::from_millis(500); // Cada cuánto se revisa si cambiaron los archivos
+const FOV_SPEED: f32 = 1.0;      // Radianes por segundo que cambia el campo de visión con [ y ]
+const FOV_SLIDER_WIDTH: usize = 320;
+const SPRITE_NEAR: f32 = 0.3; // Los sprites más cerca de la cámara que esto no se dibujan
+const MINIMAP_OPACITY: f32 = 0.75; // El minimapa deja ver la vista 3D a través
+const PAUSE_BLUR: usize = 4;        // Radio del desenfoque detrás del menú de pausa
+const PAUSE_DIM: f32 = 0.5;         // Cuánto se oscurece el juego en pausa
+const HURT_FLASH_ALPHA: f32 = 0.45; // Opacidad máxima del destello rojo al recibir un golpe
+const KILL_SCORE: u32 = 100; // Puntos por cada enemigo derribado
+// Teclas para elegir cada arma, en el orden de WeaponKind::ALL
+const WEAPON_KEYS: [Key; 3] = [Key::Key1, Key::Key2, Key::Key3];
+// Teclas para encender o apagar cada efecto de película, en el orden de PostFx::effects
+const EFFECT_KEYS: [Key; 6] = [Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6];
+const GLASS_FRAMES: usize = 12; // Cuadros del brillo del vidrio; los primeros y últimos quedan sin brillo
+
+// Las rutas de las texturas están en assets/manifest.txt
+static WALL1: Lazy<TextureHandle> = Lazy::new(|| assets::texture("wall1"));
+static WALL2: Lazy<TextureHandle> = Lazy::new(|| assets::texture("wall2"));
+static FLOOR: Lazy<TextureHandle> = Lazy::new(|| assets::texture("floor"));
+static DOOR: Lazy<TextureHandle> = Lazy::new(|| assets::texture("door"));
+// Rejas, vallas y vidrio: texturas con texels transparentes o translúcidos
+static GRATE: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("grate", Texture::from_fn(64, 64, |x, y| {
+    if x % 16 < 4 || y % 16 < 4 { 0xFF3A3A3A } else { 0x00000000 }
+})));
+static FENCE: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("fence", Texture::from_fn(64, 64, |x, y| {
+    if x % 16 < 10 || (18..24).contains(&y) || (44..50).contains(&y) { 0xFF8B5A2B } else { 0x00000000 }
+})));
+// El vidrio es una animación: un brillo diagonal que cruza el panel de vez en cuando
+static GLASS: Lazy<AnimationHandle> = Lazy::new(|| {
+    let frames = (0..GLASS_FRAMES)
+        .map(|frame| {
+            let shine = frame as i32 * 16 - 32;
+            Texture::from_fn(64, 64, move |x, y| {
+                if x < 3 || y < 3 || x > 60 || y > 60 {
+                    0xFF5A5A5A
+                } else if (x as i32 + y as i32 - shine * 2).abs() < 6 {
+                    0x90D8F0FF
+                } else {
+                    0x6088C8E8
+                }
+            })
+        })
+        .collect();
+    assets::insert_animation("glass", Animation::from_frames(frames, 0.08, LoopMode::Loop))
+});
+static SKY: Lazy<TextureHandle> = Lazy::new(|| assets::texture("sky"));
+static INTRO_ANIMATION: Lazy<AnimationHandle> = Lazy::new(|| assets::animation("intro"));
+// Lámpara para las celdas 'L': cable, pantalla de bronce y foco
+static LAMP: Lazy<TextureHandle> = Lazy::new(|| assets::insert_texture("lamp", Texture::from_fn(32, 64, |x, y| {
+    let dx = x as i32 - 16;
+    let bulb = dx * dx + (y as i32 - 50) * (y as i32 - 50);
+    if y < 34 && (15..17).contains(&x) {
+        0xFF202020
+    } else if (34..46).contains(&y) && dx.abs() <= (y as i32 - 34) + 3 {
+        if dx.abs() == (y as i32 - 34) + 3 || y == 45 { 0xFF5A3A10 } else { 0xFFB07A28 }
+    } else if y >= 46 && bulb < 36 {
+        0xFFFFF0B0
+    } else {
+        0x00000000
+    }
+})));
+const LAMP_SHAPE: SpriteShape = SpriteShape { height: 0.35, width_scale: 1.0, anchor: Anchor::Ceiling(1.0) };
+
 
-static WALL1: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("sprites/wall4.webp")));
-static WALL2: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("sprites/wall4.webp")));
-static FLOOR: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("sprites/floor7.webp")));
-static SKY: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("sprites/sky3.jpeg")));
-static ENEMY_TEXTURE: Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("sprites/cagney2.png")));
-static ENEMY_ANIM_FRAMES: Lazy<Vec<Arc<Texture>>> = Lazy::new(|| vec![
-    Arc::new(Texture::new("sprites/cagney2.png")),
-    Arc::new(Texture::new("sprites/cagney.png")),
-    Arc::new(Texture::new("sprites/cagney2.png")),
-]);
 
+// `pixels_per_unit` es cuántos pixeles de pantalla ocupa una repetición de la
+// textura y sirve para elegir el mipmap
+fn cell_to_texture_color(texture: &Texture, wall_type: char, tx: f32, ty: f32, pixels_per_unit: f32) -> u32 {
+    tint_wall(wall_type, texture.sample(tx, ty, texture.height as f32 / pixels_per_unit))
+}
+
+// Texturas de las paredes y del piso en un cuadro. Cada handle pasa por el lock del
+// administrador de assets, así que se resuelven una vez por cuadro y no por columna
+struct WallTextures {
+    wall1: Arc<Texture>,
+    wall2: Arc<Texture>,
+    door: Arc<Texture>,
+    grate: Arc<Texture>,
+    fence: Arc<Texture>,
+    glass: Arc<Texture>, // Cuadro de la animación del vidrio en este instante
+    floor: Arc<Texture>,
+}
+
+impl WallTextures {
+    // `time` elige el cuadro de las paredes animadas
+    fn resolve(time: f32) -> Self {
+        WallTextures {
+            wall1: WALL1.get(),
+            wall2: WALL2.get(),
+            door: DOOR.get(),
+            grate: GRATE.get(),
+            fence: FENCE.get(),
+            glass: GLASS.get().frame_at(time),
+            floor: FLOOR.get(),
+        }
+    }
+
+    fn wall(&self, wall_type: char) -> &Texture {
+        match wall_type {
+            '-' => &self.wall2,
+            'D' | 'R' | 'B' | 'Y' => &self.door,
+            '#' => &self.grate,
+            '=' | '!' => &self.fence,
+            '%' => &self.glass,
+            _ => &self.wall1,
+        }
+    }
+}
 
+// Lo que comparten las funciones que dibujan la vista 3D de un cuadro: el nivel, la cámara
+// y las texturas ya resueltas para este instante
+struct View<'a> {
+    world: &'a World,
+    camera: Camera,
+    camera_z: f32, // Altura de los ojos
+    horizon: f32,  // Fila de pantalla del horizonte
+    wall_textures: WallTextures,
+    time: f32,     // Segundos desde que empezó el juego; mueve las animaciones
+}
 
-fn cell_to_texture_color(wall_type: char, is_vertical: bool, tx: f32, ty: f32) -> u32 {
+// Las puertas con llave se tiñen del color de su llave
+fn tint_wall(wall_type: char, color: u32) -> u32 {
     match wall_type {
-        '|' => WALL1.get_pixel_color((tx * WALL1.width as f32) as u32, (ty * WALL1.height as f32) as u32),
-        '-' => WALL2.get_pixel_color((tx * WALL2.width as f32) as u32, (ty * WALL2.height as f32) as u32),
-        _ => WALL1.get_pixel_color((tx * WALL1.width as f32) as u32, (ty * WALL1.height as f32) as u32),
+        'R' | 'B' | 'Y' => blend(color, key_color(wall_type), 0.4),
+        _ => color,
     }
 }
 
-fn draw_cell(framebuffer: &mut Vec<u32>, xo: usize, yo: usize, block_size: usize, cell: char) {
+fn draw_cell(framebuffer: &mut Framebuffer, xo: usize, yo: usize, block_size: usize, cell: char) {
     let color = match cell {
-        '+' | '-' | '|' => 0xFFFFFF, 
+        '+' | '-' | '|' | '[' | ']' | '^' | '_' => 0xFFFFFF, 
         'p' => 0xFF0000, 
         'g' => 0x00FF00, 
+        'D' => 0x8B4513,
+        'L' => 0xFFD27F,
+        'c' => 0xB0A890,
+        '#' | '=' | '!' => 0x888888,
+        '%' => 0x88C8E8,
+        'R' | 'B' | 'Y' | 'r' | 'b' | 'y' => key_color(cell),
         _ => 0x000000,   
     };
 
-    for y in yo..(yo + block_size).min(HEIGHT) {
-        for x in xo..(xo + block_size).min(WIDTH) {
-            framebuffer[y * WIDTH + x] = color;
-        }
+    framebuffer.set_current_color(color);
+    framebuffer.fill_rect(xo as isize, yo as isize, block_size, block_size);
+}
+
+// Un punto por cada objeto sin recoger, en un mapa dibujado a `scale` pixeles por celda
+fn draw_items(framebuffer: &mut Framebuffer, items: &Items, x_offset: usize, y_offset: usize, scale: usize) {
+    for item in items.list() {
+        framebuffer.set_current_color(item.kind.color());
+        framebuffer.fill_circle(
+            (x_offset as f32 + item.pos.x * scale as f32) as isize,
+            (y_offset as f32 + item.pos.y * scale as f32) as isize,
+            (scale / 5).max(2) as isize,
+        );
     }
 }
 
-fn render2d(framebuffer: &mut Vec<u32>, maze: &Vec<Vec<char>>, block_size: usize, player: &Player) {
+fn render2d(framebuffer: &mut Framebuffer, maze: &[Vec<char>], items: &Items, block_size: usize, player: &Player) {
     for (row, line) in maze.iter().enumerate() {
         for (col, &cell) in line.iter().enumerate() {
             draw_cell(
@@ -69,51 +232,71 @@ fn render2d(framebuffer: &mut Vec<u32>, maze: &Vec<Vec<char>>, block_size: usize
             );
         }
     }
+    draw_items(framebuffer, items, 0, 0, block_size);
+
+    // Dibujar al jugador en la vista 2D: su campo de visión y un punto rojo
+    let player_x = (player.pos.x * block_size as f32) as isize;
+    let player_y = (player.pos.y * block_size as f32) as isize;
+    let player_size = (block_size / 8) as isize; // Radio del punto que representa al jugador
+    let reach = block_size as f32 * 1.5;
+    let corner = |a: f32| (player_x + (a.cos() * reach) as isize, player_y + (a.sin() * reach) as isize);
+    let left = corner(player.a - player.fov / 2.0);
+    let right = corner(player.a + player.fov / 2.0);
+
+    framebuffer.set_current_color(0x552222);
+    framebuffer.fill_triangle((player_x, player_y), left, right);
+    framebuffer.set_current_color(0xFF0000); // Rojo para representar al jugador
+    framebuffer.triangle((player_x, player_y), left, right);
+    framebuffer.fill_circle(player_x, player_y, player_size);
+    framebuffer.set_current_color(0xFFFFFF);
+    framebuffer.circle(player_x, player_y, player_size + 1);
+}
 
-    // Dibujar al jugador en la vista 2D
-    let player_x = (player.pos.x * block_size as f32) as usize;
-    let player_y = (player.pos.y * block_size as f32) as usize;
-    let player_size = block_size / 4; // Tamaño del punto que representa al jugador
+fn render_floor(framebuffer: &mut Framebuffer, view: &View) {
+    let (camera, camera_z, horizon) = (&view.camera, view.camera_z, view.horizon);
+    // Rayo de cada columna, para saber qué punto del piso se ve a cada profundidad
+    let columns: Vec<na::Vector2<f32>> = (0..WIDTH).map(|x| camera.ray_dir(x)).collect();
 
-    for y in player_y..(player_y + player_size).min(HEIGHT) {
-        for x in player_x..(player_x + player_size).min(WIDTH) {
-            framebuffer[y * WIDTH + x] = 0xFF0000; // Rojo para representar al jugador
-        }
-    }
-}
+    let floor = &view.wall_textures.floor;
 
-fn render_floor(framebuffer: &mut Vec<u32>) {
-    for y in (HEIGHT / 2)..HEIGHT {
-        let ty = (y - HEIGHT / 2) * FLOOR.height as usize / (HEIGHT / 2);
+    for y in (horizon.max(0.0) as usize)..HEIGHT {
+        let row_distance = camera.row_distance(camera_z, y as f32, horizon).min(MAX_LIGHT_DISTANCE);
+        // Pixeles por unidad del mundo en esta fila, para elegir el mipmap
+        let pixels_per_unit = camera.row_scale(camera_z, row_distance);
+        let texels_per_pixel = floor.height as f32 / pixels_per_unit;
 
-        for x in 0..WIDTH {
-            let tx = x * FLOOR.width as usize / WIDTH;
-            let color = FLOOR.get_pixel_color(tx as u32, ty as u32);
-            framebuffer[y * WIDTH + x] = color;
+        for (x, ray) in columns.iter().enumerate() {
+            // Cada celda del laberinto lleva una copia de la textura
+            let point = camera.pos + ray * row_distance;
+            let light = view.world.lighting.light_at(point.x, point.y);
+
+            let color = floor.sample(point.x.rem_euclid(1.0), point.y.rem_euclid(1.0), texels_per_pixel);
+            framebuffer.set_pixel(x, y, shade(color, light));
         }
     }
 }
 
-fn render_sky(framebuffer: &mut Vec<u32>, player_angle: f32) {
-    let sky_width = SKY.width as f32;
-    let sky_height = SKY.height as f32;
+fn render_sky(framebuffer: &mut Framebuffer, horizon: f32) {
+    let sky = SKY.get();
+    let sky_width = sky.width as f32;
+    let sky_height = sky.height as f32;
+    let hh = (HEIGHT / 2) as f32;
 
-    for y in 0..(HEIGHT / 2) {
-        let ty = (y as f32 / (HEIGHT / 2) as f32 * sky_height) as u32;
+    // El cielo se desplaza junto con el horizonte
+    for y in 0..(horizon.clamp(0.0, HEIGHT as f32) as usize) {
+        let ty = ((y as f32 - horizon + hh) / hh * sky_height).max(0.0) as u32;
 
         for x in 0..WIDTH {
             let tx = ((x as f32 / WIDTH as f32) * sky_width) as u32;
 
-            let color = SKY.get_pixel_color(tx, ty);
-            framebuffer[y * WIDTH + x] = color;
+            let color = sky.get_pixel_color(tx, ty);
+            framebuffer.set_pixel(x, y, color);
         }
     }
 }
 
-fn render_minimap(framebuffer: &mut Vec<u32>, maze: &Vec<Vec<char>>, player: &Player, block_size: usize) {
+fn render_minimap(framebuffer: &mut Framebuffer, maze: &[Vec<char>], items: &Items, player: &Player) {
     let minimap_scale = 20;
-    let minimap_width = maze[0].len() * minimap_scale;
-    let minimap_height = maze.len() * minimap_scale;
 
     // Posición del minimapa 
     let minimap_x_offset = 10;
@@ -123,245 +306,470 @@ fn render_minimap(framebuffer: &mut Vec<u32>, maze: &Vec<Vec<char>>, player: &Pl
     for (row, line) in maze.iter().enumerate() {
         for (col, &cell) in line.iter().enumerate() {
             let color = match cell {
-                '+' | '-' | '|' => 0xFFFFFF, 
+                '+' | '-' | '|' | '[' | ']' | '^' | '_' => 0xFFFFFF, 
                 'p' => 0xFF0000, 
+                'D' => 0x8B4513,
+                'L' => 0xFFD27F,
+                '#' | '=' | '!' => 0x888888,
+                '%' => 0x88C8E8,
+                'R' | 'B' | 'Y' | 'r' | 'b' | 'y' => key_color(cell),
                 _ => 0x000000,   
             };
 
-            for y in 0..minimap_scale {
-                for x in 0..minimap_scale {
-                    let pixel_x = minimap_x_offset + col * minimap_scale + x;
-                    let pixel_y = minimap_y_offset + row * minimap_scale + y;
-                    if pixel_x < WIDTH && pixel_y < HEIGHT {
-                        framebuffer[pixel_y * WIDTH + pixel_x] = color;
-                    }
-                }
-            }
+            framebuffer.set_current_color(color);
+            framebuffer.fill_rect(
+                (minimap_x_offset + col * minimap_scale) as isize,
+                (minimap_y_offset + row * minimap_scale) as isize,
+                minimap_scale,
+                minimap_scale,
+            );
         }
     }
 
-    // Dibujar al jugador en el minimapa
-    let player_minimap_x = minimap_x_offset + (player.pos.x * minimap_scale as f32) as usize;
-    let player_minimap_y = minimap_y_offset + (player.pos.y * minimap_scale as f32) as usize;
+    draw_items(framebuffer, items, minimap_x_offset, minimap_y_offset, minimap_scale);
 
-    let player_minimap_size = 8;
+    // Dibujar al jugador en el minimapa, con una línea hacia donde mira
+    let player_minimap_x = (minimap_x_offset as f32 + player.pos.x * minimap_scale as f32) as isize;
+    let player_minimap_y = (minimap_y_offset as f32 + player.pos.y * minimap_scale as f32) as isize;
+    let facing = minimap_scale as f32 * 0.75;
 
-    for y in 0..player_minimap_size {
-        for x in 0..player_minimap_size {
-            let pixel_x = player_minimap_x + x;
-            let pixel_y = player_minimap_y + y;
-            if pixel_x < WIDTH && pixel_y < HEIGHT {
-                framebuffer[pixel_y * WIDTH + pixel_x] = 0xFF0000;
-            }
+    framebuffer.set_current_color(0xFF0000);
+    framebuffer.fill_circle(player_minimap_x, player_minimap_y, 4);
+    framebuffer.line(
+        player_minimap_x,
+        player_minimap_y,
+        player_minimap_x + (player.a.cos() * facing) as isize,
+        player_minimap_y + (player.a.sin() * facing) as isize,
+    );
+}
+
+// Fila de pantalla del horizonte, desplazada por la inclinación de la cámara
+// y el balanceo al caminar
+fn horizon(player: &Player) -> f32 {
+    HEIGHT as f32 / 2.0 + player.pitch + player.bob_offset()
+}
+
+// Escribe un pixel de pared. Las paredes que dejan ver a través se mezclan con
+// lo que ya hay en el framebuffer usando el alfa de su textura
+fn put_wall_pixel(framebuffer: &mut Framebuffer, x: usize, y: usize, color: u32, see_through: bool) {
+    if see_through {
+        let alpha = color >> 24;
+        if alpha == 0 {
+            return;
         }
+        framebuffer.blend_pixel(x, y, color, alpha as f32 / 255.0);
+    } else {
+        framebuffer.set_pixel(x, y, color);
+    }
+}
+
+// Dibuja una columna de pared de la altura de su celda y, si la cámara la ve
+// desde arriba, también su cara superior
+fn draw_wall_column(framebuffer: &mut Framebuffer, view: &View, column: usize, ray_hit: &RayHit, ray: &na::Vector2<f32>) {
+    let (camera, camera_z, horizon) = (&view.camera, view.camera_z, view.horizon);
+    let lighting = &view.world.lighting;
+    // La distancia que usa la proyección; en la rectilínea evita el efecto de fisheye
+    let corrected_distance = camera.distance(ray, ray_hit.distance);
+
+    let stake_top = camera.project_y(ray_hit.height, camera_z, corrected_distance, horizon);
+    let stake_bottom = camera.project_y(0.0, camera_z, corrected_distance, horizon);
+
+    let y_start = stake_top.max(0.0) as usize;
+    let y_end = (stake_bottom.max(0.0) as usize).min(HEIGHT - 1);
+    let see_through = walls::is_see_through(ray_hit.wall_type);
+
+    // La cara de la pared recibe la luz del lado desde donde llega el rayo
+    let back = ray.normalize() * 0.01;
+    let light = lighting.light_at(ray_hit.hit_x - back.x, ray_hit.hit_y - back.y);
+
+    let stake_height = stake_bottom - stake_top;
+    // La textura se repite una vez por unidad de altura, así que el mipmap sale de los
+    // pixeles que ocupa una unidad y no de toda la columna
+    let pixels_per_unit = stake_height / ray_hit.height.max(f32::EPSILON);
+    let base = view.wall_textures.wall(ray_hit.wall_type);
+
+    if textures::bilinear() {
+        for y in y_start..y_end {
+            // Determinar la coordenada Y en la textura, que se repite en paredes altas
+            let ty = (y as f32 - stake_top) / stake_height * ray_hit.height;
+            let color = cell_to_texture_color(base, ray_hit.wall_type, ray_hit.tx, ty, pixels_per_unit);
+            put_wall_pixel(framebuffer, column, y, shade(color, light), see_through);
+        }
+    } else {
+        // Sin filtrado toda la columna de pantalla sale de una sola columna de la textura
+        let texture = base.mip(base.height as f32 / pixels_per_unit);
+        let tx = ray_hit.tx.rem_euclid(1.0);
+        let texels = texture.column((tx * texture.width as f32) as u32);
+        let step = texture.height as f32 / pixels_per_unit;
+
+        for y in y_start..y_end {
+            let ty = ((y as f32 - stake_top) * step) as u32;
+            let color = tint_wall(ray_hit.wall_type, texels[texture.wrap_y(ty)]);
+            put_wall_pixel(framebuffer, column, y, shade(color, light), see_through);
+        }
+    }
+
+    if camera_z <= ray_hit.height || ray_hit.exit_distance <= ray_hit.distance {
+        return;
+    }
+
+    // Cara superior: cada fila de pantalla corresponde a una distancia sobre el techo del bloque
+    let far_top = camera.project_y(ray_hit.height, camera_z, camera.distance(ray, ray_hit.exit_distance), horizon);
+    let top_start = far_top.max(0.0) as usize;
+    let top_end = (stake_top.max(0.0) as usize).min(HEIGHT - 1);
+
+    let floor = &view.wall_textures.floor;
+
+    for y in top_start..top_end {
+        let row_distance = camera.row_distance(camera_z - ray_hit.height, y as f32, horizon);
+        // Pixeles por unidad del mundo en esta fila, según lo que avanza la distancia de una fila a otra
+        let pixels_per_unit = camera.row_scale(camera_z - ray_hit.height, row_distance);
+        let world_x = camera.pos.x + ray.x * row_distance;
+        let world_y = camera.pos.y + ray.y * row_distance;
+        let (tx, ty) = (world_x.rem_euclid(1.0), world_y.rem_euclid(1.0));
+
+        // Las plataformas usan la textura del piso y las paredes la suya
+        let color = if walls::is_empty(ray_hit.wall_type) {
+            floor.sample(tx, ty, floor.height as f32 / pixels_per_unit)
+        } else {
+            cell_to_texture_color(base, ray_hit.wall_type, tx, ty, pixels_per_unit)
+        };
+        let light = lighting.light_at(world_x, world_y);
+        put_wall_pixel(framebuffer, column, y, shade(color, light), see_through);
     }
 }
 
-fn render3d(framebuffer: &mut Vec<u32>, maze: &Vec<Vec<char>>, player: &Player, block_size: usize, frame_time: f32) {
-    let num_rays = WIDTH;
-    let hh = HEIGHT as f32 / 2.0;
-    let mut z_buffer: Vec<f32> = vec![std::f32::MAX; WIDTH];
+// `time` son los segundos desde que empezó el juego y mueve las animaciones
+fn render3d(framebuffer: &mut Framebuffer, world: &World, entities: &[Sprite], player: &Player, block_size: usize, time: f32) {
+    let view = View {
+        world,
+        camera: Camera::new(player, WIDTH, HEIGHT),
+        camera_z: world.heights.camera_z(player),
+        horizon: horizon(player),
+        wall_textures: WallTextures::resolve(time),
+        time,
+    };
+    let (camera, camera_z) = (&view.camera, view.camera_z);
+    let mut z_buffer: Vec<f32> = vec![f32::MAX; WIDTH];
+    let mut pending: Vec<Vec<RayHit>> = Vec::with_capacity(WIDTH);
 
     // Renderizar el cielo primero
-    render_sky(framebuffer, player.a);
+    render_sky(framebuffer, view.horizon);
 
     // Luego renderizar el suelo
-    render_floor(framebuffer);
+    render_floor(framebuffer, &view);
 
     // Renderizar las paredes
-    for i in 0..num_rays {
-        let current_ray = i as f32 / num_rays as f32;
-        let a = player.a - (player.fov / 2.0) + (player.fov * current_ray);
+    for (i, depth) in z_buffer.iter_mut().enumerate() {
+        let ray = camera.ray_dir(i);
+        let a = camera.ray_angle(i);
 
-        let ray_hit = cast_ray(maze, player, a, block_size);
+        let mut ray_hits = cast_ray(&world.maze, &world.walls, &world.heights, &world.doors, player, a, block_size);
 
-        // Corregir la distancia para evitar el efecto de fisheye
-        let corrected_distance = ray_hit.distance * (a - player.a).cos();
-        z_buffer[i] = corrected_distance;
-        let stake_height = (hh / corrected_distance) as usize;
-
-        let mut stake_top = hh as usize - (stake_height / 2);
-        let mut stake_bottom = hh as usize + (stake_height / 2);
-
-        if stake_top >= HEIGHT {
-            stake_top = HEIGHT - 1;
-        }
-        if stake_bottom >= HEIGHT {
-            stake_bottom = HEIGHT - 1;
+        // Los sprites sólo quedan tapados por paredes opacas que lleguen a los ojos; detrás
+        // de rejas, vidrio o paredes bajas se siguen viendo
+        let front = ray_hits.iter().position(|hit| blocks_view(hit, camera_z)).unwrap_or(ray_hits.len());
+        if let Some(nearest) = ray_hits.get(front) {
+            *depth = camera.distance(&ray, nearest.distance);
         }
 
-        // Determinar la coordenada X en la textura
-        let texture_x = if ray_hit.is_vertical {
-            (ray_hit.hit_y % 1.0 * WALL1.width as f32) as u32
-        } else {
-            (ray_hit.hit_x % 1.0 * WALL1.width as f32) as u32
-        };
-
-        for y in stake_top..stake_bottom {
-            // Determinar la coordenada Y en la textura
-            let texture_y = ((y as f32 - stake_top as f32) / (stake_bottom as f32 - stake_top as f32) * WALL1.height as f32) as u32;
-            let color = cell_to_texture_color(ray_hit.wall_type, ray_hit.is_vertical, texture_x as f32 / WALL1.width as f32, texture_y as f32 / WALL1.height as f32);
-            framebuffer[y * WIDTH + i] = color;
+        // Dibujar de atrás hacia adelante: las paredes cercanas tapan a las lejanas
+        // y las translúcidas se mezclan con lo que hay detrás. Las que están delante de
+        // la primera pared que tapa se dibujan junto con los sprites
+        for ray_hit in ray_hits[front..].iter().rev() {
+            draw_wall_column(framebuffer, &view, i, ray_hit, &ray);
         }
+        ray_hits.truncate(front);
+        pending.push(ray_hits);
     }
 
-    // Renderizar los enemigos después de las paredes y antes de cualquier otro elemento
-    render_enemies(framebuffer, player, &mut z_buffer, frame_time);
-
-    // Llamada para dibujar el minimapa
-    render_minimap(framebuffer, maze, player, block_size);
+    // Renderizar los sprites después de las paredes y antes de cualquier otro elemento
+    render_sprites(framebuffer, &view, entities, &z_buffer, &mut pending);
 }
 
-fn render_enemy(framebuffer: &mut Vec<u32>, player: &Player, pos: &na::Vector2<f32>, z_buffer: &mut [f32], frame_time: f32) {
-    let sprite_dir = na::Vector2::new(
-        pos.x - player.pos.x,
-        pos.y - player.pos.y,
-    );
-
-    let sprite_distance = sprite_dir.norm();
-    let sprite_angle = (sprite_dir.y).atan2(sprite_dir.x) - player.a;
+// Dibuja, de atrás hacia adelante, las paredes pendientes de una columna que están más
+// lejos que `depth`. `pending` va de la más cercana a la más lejana
+fn draw_walls_behind(framebuffer: &mut Framebuffer, view: &View, column: usize, pending: &mut Vec<RayHit>, depth: f32) {
+    let ray = view.camera.ray_dir(column);
+    while pending.last().is_some_and(|hit| view.camera.distance(&ray, hit.distance) > depth) {
+        if let Some(ray_hit) = pending.pop() {
+            draw_wall_column(framebuffer, view, column, &ray_hit, &ray);
+        }
+    }
+}
 
-    let sprite_angle = if sprite_angle < -std::f32::consts::PI {
-        sprite_angle + 2.0 * std::f32::consts::PI
-    } else if sprite_angle > std::f32::consts::PI {
-        sprite_angle - 2.0 * std::f32::consts::PI
-    } else {
-        sprite_angle
+// Dibuja un sprite con su altura y anclaje. Se proyecta con la misma cámara que las paredes
+// y cada columna se compara con su z_buffer, así un sprite detrás de una esquina queda recortado.
+// Antes de cada columna se dibujan las rejas y paredes bajas que quedan detrás del sprite
+fn render_sprite(framebuffer: &mut Framebuffer, view: &View, sprite: &Sprite, z_buffer: &[f32], pending: &mut [Vec<RayHit>]) {
+    let (camera, camera_z, horizon) = (&view.camera, view.camera_z, view.horizon);
+    let pos = &sprite.pos;
+    let Some((_, depth)) = camera.project(*pos) else {
+        return;
     };
+    if depth < SPRITE_NEAR {
+        return;
+    }
 
-    if sprite_angle.abs() > player.fov / 2.0 || sprite_distance < 0.5 {
+    // Alto en pantalla a partir de la base y el borde de arriba en el mundo, y ancho a partir
+    // de sus bordes izquierdo y derecho, que se proyectan con la escala horizontal de la cámara
+    let floor = view.world.heights.floor(pos.x as usize, pos.y as usize);
+    let (bottom, top) = sprite.shape.extent(floor, view.time, sprite.phase);
+    let start_y = camera.project_y(top, camera_z, depth, horizon);
+    let sprite_height = camera.project_y(bottom, camera_z, depth, horizon) - start_y;
+    let Some((start_x, end_x)) = camera.project_span(*pos, (top - bottom) * sprite.shape.aspect(&sprite.texture)) else {
+        return;
+    };
+    let sprite_width = end_x - start_x;
+    if sprite_height < 1.0 || sprite_width < 1.0 {
         return;
     }
 
-    let screen_x = (WIDTH as f32 / 2.0) * (1.0 + sprite_angle / player.fov);
-    let sprite_height = (HEIGHT as f32 / sprite_distance) * 0.4;
-    let sprite_width = sprite_height;
+    let texture = &sprite.texture;
+    let light = view.world.lighting.light_at(pos.x, pos.y);
 
-    let start_x = screen_x as isize - (sprite_width as isize / 2);
-    let start_y = (HEIGHT as isize / 2) - (sprite_height as isize / 2);
-    let end_x = start_x + sprite_width as isize;
-    let end_y = start_y + sprite_height as isize;
+    let columns = (start_x.max(0.0) as usize)..((start_x + sprite_width).clamp(0.0, WIDTH as f32) as usize);
+    let rows = (start_y.max(0.0) as usize)..((start_y + sprite_height).clamp(0.0, HEIGHT as f32) as usize);
 
-    if start_x >= 0 && end_x < WIDTH as isize && sprite_distance < z_buffer[screen_x as usize] {
-        let frame_index = ((frame_time * 10.0) as usize) % ENEMY_ANIM_FRAMES.len();
-        let enemy_texture = &ENEMY_ANIM_FRAMES[frame_index];
+    for x in columns {
+        if depth >= z_buffer[x] {
+            continue;
+        }
+        draw_walls_behind(framebuffer, view, x, &mut pending[x], depth);
+        let tx = ((x as f32 - start_x) / sprite_width * texture.width as f32) as u32;
 
-        for x in start_x..end_x {
-            for y in start_y..end_y {
-                if x >= 0 && x < WIDTH as isize && y >= 0 && y < HEIGHT as isize {
-                    let x = x as usize;
-                    let y = y as usize;
+        for y in rows.clone() {
+            let ty = ((y as f32 - start_y) / sprite_height * texture.height as f32) as u32;
+            let color = texture.get_pixel_color(tx.min(texture.width - 1), ty.min(texture.height - 1));
 
-                    let tx = ((x - start_x as usize) * enemy_texture.width as usize / sprite_width as usize) as u32;
-                    let ty = ((y - start_y as usize) * enemy_texture.height as usize / sprite_height as usize) as u32;
-                    let color = enemy_texture.get_pixel_color(tx, ty);
+            if color != 0x000000 { // Ignorar color negro, hacerlo transparente
+                framebuffer.set_pixel(x, y, shade(color, light));
+            }
+        }
+    }
+}
 
-                    if color != 0x000000 { // Ignorar color negro, hacerlo transparente
-                        framebuffer[y * WIDTH + x] = color;
-                    }
-                }
+// Lámparas colgadas en las celdas 'L' del mapa
+fn lamp_sprites(maze: &[Vec<char>]) -> Vec<Sprite> {
+    let mut sprites = Vec::new();
+    for (y, line) in maze.iter().enumerate() {
+        for (x, &cell) in line.iter().enumerate() {
+            if lighting::is_light(cell) {
+                let pos = na::Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                sprites.push(Sprite { pos, texture: LAMP.get(), shape: LAMP_SHAPE, phase: 0.0 });
             }
         }
-        z_buffer[screen_x as usize] = sprite_distance;
     }
+    sprites
+}
+
+// Sprites de los enemigos, vistos desde `viewer`, de los objetos sin recoger y de las
+// banderas de los checkpoints
+fn world_sprites(enemies: &Enemies, items: &Items, checkpoints: &Checkpoints, viewer: na::Vector2<f32>) -> Vec<Sprite> {
+    enemies.list.iter().map(|enemy| enemy.sprite(viewer)).chain(items.sprites()).chain(checkpoints.sprites()).collect()
 }
 
-fn render_enemies(framebuffer: &mut Vec<u32>, player: &Player, z_buffer: &mut [f32], frame_time: f32) {
-    let enemy_positions = vec![
-        na::Vector2::new(2.0, 5.0),
-        na::Vector2::new(11.0, 3.5),
-        na::Vector2::new(5.0, 5.0),
-        na::Vector2::new(8.0, 7.0),
-        na::Vector2::new(7.0, 2.0), 
-    ];
+// Dibuja las entidades y las lámparas del mapa de la más lejana a la más cercana, y al
+// final las paredes pendientes que quedaron delante de todas
+fn render_sprites(framebuffer: &mut Framebuffer, view: &View, entities: &[Sprite], z_buffer: &[f32], pending: &mut [Vec<RayHit>]) {
+    let camera = &view.camera;
+    let lamps = lamp_sprites(&view.world.maze);
+    let mut sprites: Vec<&Sprite> = entities.iter().chain(&lamps).collect();
 
-    for enemy_pos in &enemy_positions {
-        render_enemy(framebuffer, player, enemy_pos, z_buffer, frame_time);
+    let depth = |sprite: &Sprite| (sprite.pos - camera.pos).dot(&camera.dir);
+    sprites.sort_by(|a, b| depth(b).total_cmp(&depth(a)));
+
+    for sprite in sprites {
+        render_sprite(framebuffer, view, sprite, z_buffer, pending);
+    }
+    for (column, walls) in pending.iter_mut().enumerate() {
+        draw_walls_behind(framebuffer, view, column, walls, 0.0);
     }
 }
 
-fn draw_fps_box(framebuffer: &mut Vec<u32>, fps: u32) {
-    let box_width = 100;
-    let box_height = 30;
-    let box_color = 0xFFFFFF; 
-    let text_color = 0x000000; 
+// Barra del campo de visión, abajo al centro, con la proyección actual
+fn draw_fov_slider(framebuffer: &mut Framebuffer, player: &Player) {
+    let x = (WIDTH - FOV_SLIDER_WIDTH) / 2;
+    let y = HEIGHT - 60;
+    let t = (player.fov - MIN_FOV) / (MAX_FOV - MIN_FOV);
+    let filled = (t.clamp(0.0, 1.0) * FOV_SLIDER_WIDTH as f32) as usize;
+
+    framebuffer.set_current_color(0x202020);
+    framebuffer.fill_rect(x as isize, y as isize, FOV_SLIDER_WIDTH, 8);
+    framebuffer.set_current_color(0xF0E8D0);
+    framebuffer.fill_rect(x as isize, y as isize, filled, 8);
+    framebuffer.set_current_color(0x000000);
+    framebuffer.rect(x as isize, y as isize, FOV_SLIDER_WIDTH, 8);
+    framebuffer.set_current_color(0xFFFFFF);
+    framebuffer.fill_circle((x + filled) as isize, y as isize + 4, 8);
+
+    let label = format!("FOV {:.0}° ({})", player.fov.to_degrees(), player.projection.name());
+    let style = TextStyle::new(22.0, 0xFFFFFF).align(Align::Center).shadow(2, 2, 0x000000);
+    font::draw_text_styled(framebuffer, &label, WIDTH / 2, y - 32, &style);
+}
 
-    for y in 0..box_height {
-        for x in 0..box_width {
-            framebuffer[y * WIDTH + x] = box_color;
-        }
+// Inventario en la esquina inferior izquierda: un ícono por tipo de objeto con su
+// cantidad, las llaves que se tienen y el puntaje
+fn draw_inventory(framebuffer: &mut Framebuffer, inventory: &Inventory) {
+    const ICON: usize = 28;
+    let style = TextStyle::new(22.0, 0xFFFFFF).shadow(2, 2, 0x000000);
+    let y = HEIGHT - ICON - 12;
+    let mut x = 12;
+
+    let counts = [(ItemKind::Coin, inventory.coins), (ItemKind::Health, inventory.health), (ItemKind::PowerUp, inventory.power_ups)];
+    for (kind, count) in counts {
+        framebuffer.blit(&kind.texture(), x as isize, y as isize, ICON, ICON, 1.0);
+        let text = format!("x{}", count);
+        font::draw_text_styled(framebuffer, &text, x + ICON + 4, y + 2, &style);
+        x += ICON + 16 + font::measure(&text, style.size).0;
+    }
+    for &key in &inventory.keys {
+        framebuffer.blit(&ItemKind::Key(key).texture(), x as isize, (y + ICON / 4) as isize, ICON, ICON / 2, 1.0);
+        x += ICON + 6;
     }
 
-    let fps_text = format!("FPS: {}", fps);
-    let start_x = 10;
-    let start_y = 10;
+    font::draw_text_styled(framebuffer, &format!("Score: {}", inventory.score), 12, y - 30, &style);
+}
 
-    for (i, c) in fps_text.chars().enumerate() {
-        let x = start_x + i * 8;
-        let y = start_y;
-        if x < WIDTH && y < HEIGHT {
-            framebuffer[y * WIDTH + x] = text_color;
-        }
+// Corazones de vida debajo del FPS; los perdidos quedan casi transparentes
+fn draw_health(framebuffer: &mut Framebuffer, player: &Player) {
+    const ICON: usize = 28;
+    let heart = ItemKind::Health.texture();
+    for i in 0..MAX_HEALTH as usize {
+        let x = WIDTH - 10 - (MAX_HEALTH as usize - i) * (ICON + 4);
+        let alpha = if i < player.health as usize { 1.0 } else { 0.25 };
+        framebuffer.blit(&heart, x as isize, 60, ICON, ICON, alpha);
     }
 }
 
-fn render_text(
-    framebuffer: &mut Vec<u32>, 
-    text: &str, 
-    x: usize, 
-    y: usize, 
-    scale: Scale, 
-    color: u32
-) {
-    let font_data = include_bytes!(r#"C:\Users\esteb\OneDrive\Escritorio\Universidad\2024\Graficas por Computadora\GraficasProyecto1Raycasting\assets\font.ttf"#);
-    let font = Font::try_from_bytes(font_data as &[u8]).unwrap();
+// Arma elegida y sus balas, debajo de los corazones
+fn draw_weapon(framebuffer: &mut Framebuffer, weapons: &Weapons) {
+    let stats = weapons.current.stats();
+    let text = match (weapons.ammo(), stats.max_ammo) {
+        (Some(ammo), Some(max)) => format!("{} {}/{}", stats.name, ammo, max),
+        _ => stats.name.to_string(),
+    };
+    let style = TextStyle::new(22.0, 0xFFFFFF).align(Align::Right).shadow(2, 2, 0x000000);
+    font::draw_text_styled(framebuffer, &text, WIDTH - 10, 96, &style);
+}
 
-    let v_metrics = font.v_metrics(scale);
+// Tiñe todo el cuadro de un color, para los destellos de pantalla completa
+fn flash_screen(framebuffer: &mut Framebuffer, color: u32, amount: f32) {
+    if amount <= 0.0 {
+        return;
+    }
+    for pixel in framebuffer.buffer.iter_mut() {
+        *pixel = 0xFF000000 | blend(*pixel, color, amount);
+    }
+}
 
-    let glyphs: Vec<_> = font
-        .layout(text, scale, rusttype::point(x as f32, y as f32 + v_metrics.ascent))
-        .collect();
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "on" } else { "off" }
+}
 
-    for glyph in glyphs {
-        if let Some(bb) = glyph.pixel_bounding_box() {
-            glyph.draw(|gx, gy, gv| {
-                let px = (gx as i32 + bb.min.x) as usize;
-                let py = (gy as i32 + bb.min.y) as usize;
-
-                if px < WIDTH && py < HEIGHT {
-                    let alpha = (gv * 255.0) as u32;
-                    let foreground = if alpha > 128 {
-                        color & 0xFFFFFF
-                    } else {
-                        framebuffer[py * WIDTH + px]
-                    };
-
-                    framebuffer[py * WIDTH + px] = foreground;
-                }
-            });
-        }
+// Carga todas las texturas al iniciar, así el reporte de assets las incluye
+fn preload_textures() {
+    for texture in [&WALL1, &WALL2, &FLOOR, &DOOR, &SKY, &LAMP] {
+        Lazy::force(texture);
+    }
+    items::preload();
+    checkpoints::preload();
+    weapons::preload();
+    for animation in [&GLASS, &INTRO_ANIMATION] {
+        Lazy::force(animation);
     }
+    enemies::preload();
 }
 
+// Dibuja un cuadro de la intro estirado a toda la pantalla
+fn render_frame(framebuffer: &mut Framebuffer, frame: &Texture) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    framebuffer.blit(frame, 0, 0, width, height, 1.0);
+}
+
+// Empieza una grabación o termina la que está en curso. Devuelve el mensaje de estado
+fn toggle_recording(recorder: &mut Option<Recorder>, format: RecordFormat) -> String {
+    if let Some(active) = recorder.take() {
+        return finish_recording(active);
+    }
 
-fn load_frame(file_path: &str) -> DynamicImage {
-    image::open(file_path).expect("Failed to load frame")
+    let path = match format {
+        RecordFormat::Gif => capture::default_path("recording", "gif"),
+        RecordFormat::PngSequence => capture::default_path("recording", ""),
+    };
+    match Recorder::start(format, path) {
+        Ok(started) => {
+            let message = format!("Recording to {}", started.path().display());
+            *recorder = Some(started);
+            message
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            format!("Recording failed: {}", error)
+        }
+    }
 }
 
-fn render_frame(framebuffer: &mut Vec<u32>, frame: &DynamicImage) {
-    let frame_rgb = frame.to_rgba8();
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            if x < frame.width() as usize && y < frame.height() as usize {
-                let pixel = frame_rgb.get_pixel(x as u32, y as u32);
-                let color = ((pixel[3] as u32) << 24) | ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32);
-                framebuffer[y * WIDTH + x] = color;
-            }
+fn finish_recording(recorder: Recorder) -> String {
+    match recorder.finish() {
+        Ok((path, frames)) => format!("Saved {} frames to {}", frames, path.display()),
+        Err(error) => {
+            eprintln!("{}", error);
+            format!("Recording failed: {}", error)
+        }
+    }
+}
+
+// Termina de escribir la partida grabada. Devuelve el mensaje de estado
+fn finish_replay(replay: ReplayWriter) -> String {
+    match replay.finish() {
+        Ok((path, frames)) => format!("Saved {} replay frames to {}", frames, path.display()),
+        Err(error) => {
+            eprintln!("{}", error);
+            format!("Replay failed: {}", error)
         }
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // Sin ventana: si el nivel, la partida grabada o la salida fallan, no hay nada que
+    // medir ni grabar
+    let headless = if args.iter().any(|arg| arg == "--bench") {
+        Some(benchmark::run())
+    } else {
+        args.iter().position(|arg| arg == "--record").map(|index| {
+            let Some(replay_path) = args.get(index + 1) else {
+                eprintln!("--record needs a replay file saved with --save-replay");
+                std::process::exit(1);
+            };
+            let path = args.get(index + 2).map_or_else(|| capture::default_path("replay", "gif"), std::path::PathBuf::from);
+            benchmark::record(std::path::Path::new(replay_path), &path)
+        })
+    };
+    if let Some(result) = headless {
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Con --save-replay se guarda cada cuadro de la partida para repetirla con --record
+    let mut replay = match args.iter().position(|arg| arg == "--save-replay") {
+        Some(index) => {
+            let path = args.get(index + 1).map_or_else(|| capture::default_path("replay", "replay"), std::path::PathBuf::from);
+            match ReplayWriter::create(path) {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
     let mut window = Window::new(
         "Maze",
         WIDTH,
@@ -372,89 +780,179 @@ fn main() {
         panic!("{}", e);
     });
 
-    let mut framebuffer: Vec<u32> = vec![0; WIDTH * HEIGHT];
+    // Capas: el mundo abajo, encima el minimapa semitransparente, el HUD y los menús
+    let mut compositor = Compositor::new(WIDTH, HEIGHT);
+    compositor.target(Layer::World).set_background_color(0x000000);
+    compositor.layer(Layer::Minimap).opacity = MINIMAP_OPACITY;
+    let overlay = compositor.layer(Layer::Overlay);
+    overlay.visible = false;
+    overlay.blur = PAUSE_BLUR;
+    overlay.dim = PAUSE_DIM;
+    let mut postfx = PostFx::new();
     let block_size = 80;
 
-    let frames = vec![
-        load_frame("assets/introframe1.jpeg").resize_exact(WIDTH as u32, HEIGHT as u32, image::imageops::FilterType::Lanczos3),
-        load_frame("assets/introframe2.jpeg").resize_exact(WIDTH as u32, HEIGHT as u32, image::imageops::FilterType::Lanczos3),
-        load_frame("assets/introframe3.jpeg").resize_exact(WIDTH as u32, HEIGHT as u32, image::imageops::FilterType::Lanczos3),
-    ];
+    // Música de fondo y sonido para los pasos; sin el archivo quedan en silencio
+    let background_music = AudioPlayer::new(assets::sound("music"));
+    let steps_sound = AudioPlayer::new(assets::sound("steps"));
 
-    let mut frame_index = 0;
-    let mut last_frame_time = Instant::now();
+    let level = assets::level("main");
+    let mut maze = level.get().maze.clone();
+    let mut items = Items::from_maze(&mut maze);
 
-    // Bucle de bienvenida
-    while window.is_open() && !window.is_key_down(Key::Enter) {
-        if last_frame_time.elapsed() >= Duration::from_millis(500) {
-            frame_index = (frame_index + 1) % frames.len();
-            last_frame_time = Instant::now();
-        }
+    preload_textures();
+    assets::report();
 
-        render_frame(&mut framebuffer, &frames[frame_index]);
+    let intro_start = Instant::now();
 
-        let scale = Scale::uniform(40.0);
-        let welcome_text = "Welcome to the Cuphead Maze Game!";
-        let instruction_text = "Press Enter to Start";
-        
-        let text_width = welcome_text.len() * 20;
-        let instruction_width = instruction_text.len() * 20;
-        let x_pos = (WIDTH - text_width) / 2;
+    // Bucle de bienvenida
+    while window.is_open() && !window.is_key_down(Key::Enter) {
+        compositor.clear();
+        render_frame(compositor.target(Layer::World), &INTRO_ANIMATION.get().frame_at(intro_start.elapsed().as_secs_f32()));
 
-        let y_pos = (HEIGHT / 3) + 350; 
-        let instruction_x_pos = (WIDTH - instruction_width) / 2;
-        let instruction_y_pos = y_pos + 80; 
-        
-        render_text(&mut framebuffer, welcome_text, x_pos, y_pos, scale, 0x000000);
-        render_text(&mut framebuffer, instruction_text, instruction_x_pos, instruction_y_pos, scale, 0x000000);
+        // Texto centrado, con contorno claro para que se lea sobre la imagen
+        let style = TextStyle::new(40.0, 0x000000).align(Align::Center).outline(2, 0xFFFFFF);
+        let welcome_text = "Welcome to the Cuphead Maze Game!\nPress Enter to Start";
+        font::draw_text_styled(compositor.target(Layer::Hud), welcome_text, WIDTH / 2, (HEIGHT / 3) + 350, &style);
 
-        window.update_with_buffer(&framebuffer, WIDTH, HEIGHT).unwrap();
+        let frame = compositor.composite();
+        postfx.apply(frame);
+        window.update_with_buffer(&frame.buffer, WIDTH, HEIGHT).unwrap();
 
         std::thread::sleep(Duration::from_millis(16));
     }
 
     // Música de fondo
-    let background_music = AudioPlayer::new("assets/FloralFury.mp3").expect("Failed to initialize background music");
     background_music.set_volume(0.2);
     background_music.play();
 
-    // Sonido para los pasos
-    let steps_sound = AudioPlayer::new("assets/footsteps.mp3").expect("Failed to initialize steps sound");
+    let mut thin_walls = false;
+    let mut checkpoints = Checkpoints::from_maze(&maze);
+    let mut world = World::new(maze, level.get().heights.as_deref().unwrap_or_default(), thin_walls);
+    let mut enemies = Enemies::spawn();
+    let mut weapons = Weapons::new();
+    let mut projectiles = Projectiles::new();
 
-    let enemy_positions = vec![
-        na::Vector2::new(2.0, 5.0),
-        na::Vector2::new(11.0, 3.5),
-        na::Vector2::new(5.0, 5.0),
-        na::Vector2::new(8.0, 7.0),
-    ];
-
-    let maze = maze::load_maze("maze.txt");
-
-    let mut player = Player {
-        pos: na::Vector2::new(1.5, 1.5),
-        a: std::f32::consts::FRAC_PI_3,
-        fov: std::f32::consts::FRAC_PI_3,
-    };
+    let (start_pos, start_angle) = (na::Vector2::new(1.5, 1.5), std::f32::consts::FRAC_PI_3);
+    let mut player = Player::new(start_pos, start_angle);
 
     let mut mode = "3D";
+    let mut paused = false;
+    let mut game_over = false;
+    let mut recorder: Option<Recorder> = None;
 
     let mut last_time = Instant::now();
+    let game_start = Instant::now();
     let mut frame_count = 0;
     let mut fps_text = String::new();
+    let mut status: Option<(String, Instant)> = replay.as_ref().map(|writer| (format!("Saving replay to {}", writer.path().display()), Instant::now()));
+    let mut fov_changed: Option<Instant> = None;
+    let mut last_frame = Instant::now();
+    let mut last_asset_poll = Instant::now();
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
         let frame_start_time = Instant::now();
+        let dt = frame_start_time.duration_since(last_frame).as_secs_f32();
+        last_frame = frame_start_time;
+
+        // Recargar texturas y mapas editados mientras el juego corre
+        if last_asset_poll.elapsed() >= ASSET_POLL_INTERVAL {
+            last_asset_poll = frame_start_time;
+
+            if assets::reload_changed().iter().any(|id| id == "main") {
+                let reloaded = level.get();
+                let torch = world.lighting.torch;
+
+                world.maze = reloaded.maze.clone();
+                // Los objetos que el jugador ya recogió y los puntos de control ya alcanzados
+                // no vuelven a aparecer
+                items.reload(&mut world.maze);
+                checkpoints.reload(&world.maze);
+                world.doors.reload(&world.maze);
+                world.walls = WallMap::from_maze(&world.maze, thin_walls);
+                world.heights = HeightMap::load(reloaded.heights.as_deref().unwrap_or_default(), &world.maze);
+                world.lighting = Lighting::bake(&world.maze);
+                world.lighting.torch = torch;
+
+                // Si el mapa nuevo dejó al jugador dentro de una pared o fuera del mapa,
+                // vuelve al inicio del nivel
+                if !can_stand(&world.maze, &world.walls, &world.doors, &player.pos) {
+                    player.pos = start_pos;
+                    player.a = start_angle;
+                }
+                status = Some(("Level reloaded".to_string(), Instant::now()));
+            }
+        }
+
+        // En pausa o en la pantalla de Game Over el mundo se queda quieto
+        if !paused && !game_over {
+            // Ahora el block_size está definido en este ámbito
+            process_events(&window, &mut player, &mut world, &steps_sound);
+            world.doors.update(dt);
+
+            // Destello al recoger un objeto
+            for item in items.collect(&mut player) {
+                world.lighting.flash(item.pos, 1.0, 0.5);
+                // Los potenciadores llenan las balas de todas las armas
+                if item.kind == ItemKind::PowerUp {
+                    weapons.refill();
+                }
+            }
+            if checkpoints.update(&player, &items, &enemies, &world.doors, &weapons) {
+                world.lighting.flash(player.pos, 1.0, 0.5);
+                status = Some(("Checkpoint reached".to_string(), Instant::now()));
+            }
+            world.lighting.update(dt, &player);
+
+            // Disparar con Espacio; 1, 2 y 3 eligen el arma
+            for (&key, kind) in WEAPON_KEYS.iter().zip(WeaponKind::ALL) {
+                if window.is_key_pressed(key, KeyRepeat::No) {
+                    weapons.select(kind);
+                }
+            }
+            weapons.update(dt);
+            let fire = window.is_key_down(Key::Space);
+            if fire && weapons.fire(&player, &world, &mut projectiles) {
+                world.lighting.flash(player.pos, 0.4, 0.1);
+            }
+            let kills = projectiles.update(dt, &world, &mut enemies);
+            player.inventory.score += kills as u32 * KILL_SCORE;
+
+            player.update(dt);
+            let (damage, strikes) = enemies.update(dt, &world, &player);
+            // Destello donde un enemigo acertó un ataque
+            for pos in strikes {
+                world.lighting.flash(pos, 0.8, 0.2);
+            }
+            if player.damage(damage) && player.is_dead() {
+                game_over = true;
+            }
 
-        // Ahora el block_size está definido en este ámbito
-        process_events(&window, &mut player, &maze, block_size, &steps_sound);
+            if let Some(writer) = replay.as_mut() {
+                let interact = window.is_key_pressed(Key::E, KeyRepeat::No);
+                let frame = ReplayFrame::new(&player, weapons.current, fire, interact, world.lighting.torch);
+                if let Err(error) = writer.push(frame) {
+                    eprintln!("{}", error);
+                    status = Some((format!("Replay failed: {}", error), Instant::now()));
+                    replay = None;
+                }
+            }
+            // La repetición no sabe reiniciar el nivel, así que termina en el primer Game Over
+            if game_over {
+                if let Some(writer) = replay.take() {
+                    status = Some((finish_replay(writer), Instant::now()));
+                }
+            }
+        }
 
-        framebuffer.iter_mut().for_each(|pixel| *pixel = 0);
-        let mut z_buffer: Vec<f32> = vec![std::f32::MAX; WIDTH];
+        compositor.clear();
 
         if mode == "2D" {
-            render2d(&mut framebuffer, &maze, block_size, &player);
+            render2d(compositor.target(Layer::World), &world.maze, &items, block_size, &player);
         } else {
-            render3d(&mut framebuffer, &maze, &player, block_size, Instant::now().duration_since(last_time).as_secs_f32());
+            let mut sprites = world_sprites(&enemies, &items, &checkpoints, player.pos);
+            sprites.extend(projectiles.sprites());
+            render3d(compositor.target(Layer::World), &world, &sprites, &player, block_size, game_start.elapsed().as_secs_f32());
+            render_minimap(compositor.target(Layer::Minimap), &world.maze, &items, &player);
         }
 
         // Calcular FPS
@@ -469,26 +967,177 @@ fn main() {
             frame_count = 0;
         }
 
-        let box_width = 100;
-        let box_height = 40;
+        // La caja del FPS se ajusta al texto, con 10 pixeles de margen
+        let fps_style = TextStyle::new(24.0, 0x000000).align(Align::Center);
+        let (text_width, text_height) = font::measure(&fps_text, fps_style.size);
+        let box_width = text_width.max(80) + 20;
+        let box_height = text_height + 10;
         let box_x = WIDTH - box_width - 10; 
         let box_y = 10; 
 
-        for y in box_y..(box_y + box_height) {
-            for x in box_x..(box_x + box_width) {
-                framebuffer[y * WIDTH + x] = 0xFFFFFF; 
+        let hud = compositor.target(Layer::Hud);
+        hud.set_current_color(0xFFFFFF);
+        hud.fill_rect(box_x as isize, box_y as isize, box_width, box_height);
+        hud.set_current_color(0x000000);
+        hud.rect(box_x as isize, box_y as isize, box_width, box_height);
+
+        font::draw_text_box(hud, &fps_text, box_x, box_y + 5, box_width, &fps_style);
+
+        // Mensaje breve al cambiar una opción, en la esquina inferior derecha
+        if let Some((text, shown_at)) = &status {
+            if shown_at.elapsed() < STATUS_DURATION {
+                let style = TextStyle::new(22.0, 0xFFFFFF).align(Align::Right).shadow(2, 2, 0x000000);
+                font::draw_text_styled(hud, text, WIDTH - 10, HEIGHT - 40, &style);
+            }
+        }
+
+        draw_inventory(hud, &player.inventory);
+        draw_health(hud, &player);
+        draw_weapon(hud, &weapons);
+
+        if fov_changed.is_some_and(|changed| changed.elapsed() < STATUS_DURATION) {
+            draw_fov_slider(hud, &player);
+        }
+
+        // Menú de pausa y Game Over: desenfocan y oscurecen todo lo que queda debajo
+        compositor.layer(Layer::Overlay).visible = paused || game_over;
+        if game_over {
+            let title = TextStyle::new(72.0, 0xE8303C).align(Align::Center).outline(3, 0x000000);
+            font::draw_text_styled(compositor.target(Layer::Overlay), "Game Over", WIDTH / 2, HEIGHT / 2 - 100, &title);
+            let style = TextStyle::new(32.0, 0xFFFFFF).align(Align::Center).outline(2, 0x000000);
+            let options = "Press Enter to restart from the last checkpoint\nPress R to restart the level";
+            font::draw_text_styled(compositor.target(Layer::Overlay), options, WIDTH / 2, HEIGHT / 2, &style);
+        } else if paused {
+            let style = TextStyle::new(48.0, 0xFFFFFF).align(Align::Center).outline(2, 0x000000);
+            font::draw_text_styled(compositor.target(Layer::Overlay), "Paused\nPress P to Resume", WIDTH / 2, HEIGHT / 2 - 50, &style);
+        }
+
+        let frame = compositor.composite();
+        postfx.apply(frame);
+        // Después del filtro, así el sepia no le quita el rojo
+        flash_screen(frame, 0xFF0000, player.hurt_flash() * HURT_FLASH_ALPHA);
+
+        // F12 guarda una captura; F10 graba un GIF y F11 una secuencia de PNG
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            let message = match capture::screenshot(frame) {
+                Ok(path) => format!("Screenshot saved to {}", path.display()),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    format!("Screenshot failed: {}", error)
+                }
+            };
+            status = Some((message, Instant::now()));
+        }
+        for (key, format) in [(Key::F10, RecordFormat::Gif), (Key::F11, RecordFormat::PngSequence)] {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                status = Some((toggle_recording(&mut recorder, format), Instant::now()));
             }
         }
 
-        let scale = Scale::uniform(24.0);
-        render_text(&mut framebuffer, &fps_text, box_x + 10, box_y + 10, scale, 0x000000);
+        // El indicador se dibuja después de grabar el cuadro, así no sale en la grabación
+        if let Some(active) = recorder.as_mut() {
+            active.push(frame);
+            frame.set_current_color(0xFF0000);
+            frame.fill_circle(24, HEIGHT as isize - 28, 8);
+            font::draw_text_styled(frame, "REC", 40, HEIGHT - 42, &TextStyle::new(22.0, 0xFF0000));
+        }
+
+        window.update_with_buffer(&frame.buffer, WIDTH, HEIGHT).unwrap();
 
-        window.update_with_buffer(&framebuffer, WIDTH, HEIGHT).unwrap();
+        if window.is_key_pressed(Key::P, KeyRepeat::No) && !game_over {
+            paused = !paused;
+        }
+
+        // Después de morir: Enter vuelve al último checkpoint y R al principio del nivel.
+        // Sin checkpoints alcanzados los dos empiezan de nuevo
+        if game_over {
+            let from_checkpoint = window.is_key_pressed(Key::Enter, KeyRepeat::No);
+            if from_checkpoint || window.is_key_pressed(Key::R, KeyRepeat::No) {
+                match checkpoints.save().filter(|_| from_checkpoint) {
+                    Some(save) => {
+                        player.respawn(save.pos, save.angle);
+                        player.inventory = save.inventory.clone();
+                        items = save.items.clone();
+                        enemies = save.enemies.clone();
+                        world.doors = save.doors.clone();
+                        weapons = save.weapons.clone();
+                    }
+                    None => {
+                        world.maze = level.get().maze.clone();
+                        items = Items::from_maze(&mut world.maze);
+                        checkpoints = Checkpoints::from_maze(&world.maze);
+                        world.doors = Doors::from_maze(&world.maze);
+                        player.respawn(start_pos, start_angle);
+                        player.inventory = Inventory::default();
+                        enemies = Enemies::spawn();
+                        weapons = Weapons::new();
+                    }
+                }
+                projectiles.clear();
+                game_over = false;
+            }
+        }
 
         if window.is_key_down(Key::M) {
             mode = if mode == "2D" { "3D" } else { "2D" };
         }
 
+        // Filtrado bilineal con G y mipmaps con H
+        if window.is_key_pressed(Key::G, KeyRepeat::No) {
+            textures::set_bilinear(!textures::bilinear());
+            status = Some((format!("Bilinear filtering: {}", on_off(textures::bilinear())), Instant::now()));
+        }
+        if window.is_key_pressed(Key::H, KeyRepeat::No) {
+            textures::set_mipmaps(!textures::mipmaps());
+            status = Some((format!("Mipmaps: {}", on_off(textures::mipmaps())), Instant::now()));
+        }
+
+        // Filtro de película: V lo enciende o apaga entero, F1 a F6 cada efecto por separado.
+        // Encender un efecto también enciende el filtro
+        if window.is_key_pressed(Key::V, KeyRepeat::No) {
+            postfx.enabled = !postfx.enabled;
+            status = Some((format!("Film filter: {}", on_off(postfx.enabled)), Instant::now()));
+        }
+        let mut effect_on = false;
+        for (&key, (name, enabled)) in EFFECT_KEYS.iter().zip(postfx.effects()) {
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                *enabled = !*enabled;
+                effect_on |= *enabled;
+                status = Some((format!("{}: {}", name, on_off(*enabled)), Instant::now()));
+            }
+        }
+        if effect_on {
+            postfx.enabled = true;
+        }
+
+        // Campo de visión con [ y ], y C cambia la proyección
+        let fov_direction = window.is_key_down(Key::RightBracket) as i32 - window.is_key_down(Key::LeftBracket) as i32;
+        if fov_direction != 0 {
+            player.fov = (player.fov + fov_direction as f32 * FOV_SPEED * dt).clamp(MIN_FOV, MAX_FOV);
+            fov_changed = Some(Instant::now());
+        }
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            player.projection = player.projection.next();
+            fov_changed = Some(Instant::now());
+        }
+
+        // Encender o apagar la antorcha con F
+        if window.is_key_pressed(Key::F, KeyRepeat::No) {
+            world.lighting.torch = !world.lighting.torch;
+            status = Some((format!("Torch: {}", on_off(world.lighting.torch)), Instant::now()));
+        }
+
+        // Alternar entre paredes gruesas y paredes delgadas entre celdas
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            let toggled = WallMap::from_maze(&world.maze, !thin_walls);
+            // No cambiar si el jugador quedaría dentro de un bloque
+            if !matches!(toggled.shape(player.pos.x as usize, player.pos.y as usize), WallShape::Block) {
+                thin_walls = !thin_walls;
+                world.walls = toggled;
+                status = Some((format!("Thin walls: {}", on_off(thin_walls)), Instant::now()));
+            }
+        }
+
         let frame_end_time = Instant::now();
         let frame_duration_actual = frame_end_time.duration_since(frame_start_time);
         if frame_duration_actual < std::time::Duration::from_millis(16) {
@@ -498,4 +1147,16 @@ fn main() {
             }
         }
     }
+
+    // Cerrar la grabación pendiente para que el archivo quede completo. Ya no hay HUD
+    // donde mostrar el resultado, así que va a stderr
+    if let Some(active) = recorder {
+        match active.finish() {
+            Ok((path, frames)) => eprintln!("Saved {} frames to {}", frames, path.display()),
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+    if let Some(writer) = replay {
+        eprintln!("{}", finish_replay(writer));
+    }
 }
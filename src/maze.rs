@@ -1,12 +1,31 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use crate::assets::AssetError;
 
-pub fn load_maze(filename: &str) -> Vec<Vec<char>> {
-    let file = File::open(filename).unwrap();
+// Cuarto vacío para poder jugar aunque falte el archivo del nivel
+const FALLBACK_MAZE: [&str; 5] = [
+    "+--+--+--+",
+    "|        |",
+    "+        +",
+    "|        |",
+    "+--+--+--+",
+];
+
+pub fn load_maze(filename: &str) -> Result<Vec<Vec<char>>, AssetError> {
+    let file = File::open(filename).map_err(|e| AssetError::io(filename, e))?;
     let reader = BufReader::new(file);
 
     reader
         .lines()
-        .map(|line| line.unwrap().chars().collect())
+        .map(|line| line.map(|line| line.chars().collect()).map_err(|e| AssetError::io(filename, e)))
         .collect()
 }
+
+pub fn fallback_maze() -> Vec<Vec<char>> {
+    from_rows(&FALLBACK_MAZE)
+}
+
+// Laberinto a partir de sus filas de texto, como las del archivo del nivel
+pub fn from_rows(rows: &[&str]) -> Vec<Vec<char>> {
+    rows.iter().map(|row| row.chars().collect()).collect()
+}
@@ -1,7 +1,125 @@
 use nalgebra::Vector2;
+use crate::camera::Projection;
+use crate::items::Inventory;
+
+const BOB_AMPLITUDE: f32 = 6.0; // Pixeles que sube y baja la vista al caminar
+pub const MAX_HEALTH: u32 = 5;
+const INVULNERABLE_TIME: f32 = 1.5; // Segundos sin recibir daño después de un golpe
+const HURT_FLASH_TIME: f32 = 0.4;   // Segundos que dura el destello rojo de la pantalla
 
 pub struct Player {
     pub pos: Vector2<f32>,
     pub a: f32,  // Ángulo de vista
     pub fov: f32, // Campo de visión
+    pub projection: Projection, // Cómo se reparte el campo de visión por la pantalla
+    pub inventory: Inventory, // Monedas, llaves y demás objetos recogidos
+    pub health: u32,
+    pub invulnerable: f32, // Segundos que quedan sin recibir daño
+    pub camera_height: f32, // Altura de los ojos sobre el piso
+    pub pitch: f32, // Desplazamiento vertical del horizonte en pixeles (positivo mira hacia arriba)
+    pub head_bob: bool, // Balanceo de la cámara al caminar
+    pub bob_phase: f32,
+    pub bob_amount: f32, // 0.0 quieto, 1.0 caminando a toda velocidad
+}
+
+impl Player {
+    pub fn new(pos: Vector2<f32>, a: f32) -> Self {
+        Player {
+            pos,
+            a,
+            fov: std::f32::consts::FRAC_PI_3,
+            projection: Projection::Rectilinear,
+            inventory: Inventory::default(),
+            health: MAX_HEALTH,
+            invulnerable: 0.0,
+            camera_height: 0.5,
+            pitch: 0.0,
+            head_bob: true,
+            bob_phase: 0.0,
+            bob_amount: 0.0,
+        }
+    }
+
+    // Vuelve a empezar en `pos` con la vida llena; las opciones de cámara se mantienen
+    pub fn respawn(&mut self, pos: Vector2<f32>, a: f32) {
+        self.pos = pos;
+        self.a = a;
+        self.pitch = 0.0;
+        self.health = MAX_HEALTH;
+        self.invulnerable = 0.0;
+    }
+
+    // Resta vida salvo durante la invulnerabilidad que deja el golpe anterior; devuelve
+    // si el golpe entró
+    pub fn damage(&mut self, amount: u32) -> bool {
+        if amount == 0 || self.invulnerable > 0.0 || self.is_dead() {
+            return false;
+        }
+        self.health = self.health.saturating_sub(amount);
+        self.invulnerable = INVULNERABLE_TIME;
+        true
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.health = (self.health + amount).min(MAX_HEALTH);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+    }
+
+    // Intensidad del destello rojo: 1.0 justo al recibir el golpe, se apaga enseguida
+    pub fn hurt_flash(&self) -> f32 {
+        ((self.invulnerable - (INVULNERABLE_TIME - HURT_FLASH_TIME)) / HURT_FLASH_TIME).max(0.0)
+    }
+
+    // Desplazamiento del horizonte por el balanceo al caminar
+    pub fn bob_offset(&self) -> f32 {
+        if self.head_bob {
+            self.bob_phase.sin() * BOB_AMPLITUDE * self.bob_amount
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invulnerable_after_a_hit() {
+        let mut player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        assert!(!player.damage(0));
+        assert!(player.damage(1));
+        assert_eq!(player.health, MAX_HEALTH - 1);
+
+        // Los golpes durante la invulnerabilidad no cuentan
+        assert!(!player.damage(1));
+        player.update(INVULNERABLE_TIME - 0.1);
+        assert!(!player.damage(1));
+        assert_eq!(player.health, MAX_HEALTH - 1);
+
+        player.update(0.2);
+        assert!(player.damage(2));
+        assert_eq!(player.health, MAX_HEALTH - 3);
+    }
+
+    #[test]
+    fn dead_player_takes_no_damage() {
+        let mut player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        assert!(player.damage(MAX_HEALTH + 3));
+        assert!(player.is_dead());
+        player.update(INVULNERABLE_TIME);
+        assert!(!player.damage(1));
+        assert_eq!(player.health, 0);
+
+        player.respawn(Vector2::new(2.5, 2.5), 0.0);
+        assert_eq!(player.health, MAX_HEALTH);
+        assert_eq!(player.invulnerable, 0.0);
+    }
 }
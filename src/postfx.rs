@@ -0,0 +1,251 @@
+use crate::framebuffer::Framebuffer;
+
+// Efectos de película de los años 30 sobre el cuadro ya compuesto. Todos los efectos por
+// pixel se aplican en una sola pasada con aritmética entera; las rayas van encima al final
+
+// Grano de película: ruido distinto en cada cuadro
+pub struct Grain {
+    pub enabled: bool,
+    pub strength: u32, // Amplitud del ruido, de 0 a 255
+}
+
+// Pasa los colores a tonos sepia
+pub struct Sepia {
+    pub enabled: bool,
+    pub amount: f32, // 0.0 deja el color original, 1.0 es sepia completo
+}
+
+// Oscurece los bordes de la pantalla
+pub struct Vignette {
+    pub enabled: bool,
+    pub strength: f32, // Cuánto se oscurecen las esquinas, de 0.0 a 1.0
+    pub radius: f32,   // Distancia al centro (1.0 = esquina) donde empieza a oscurecer
+}
+
+// Variaciones de brillo de un cuadro a otro, como un proyector viejo
+pub struct Flicker {
+    pub enabled: bool,
+    pub amount: f32, // Máxima pérdida de brillo, de 0.0 a 1.0
+}
+
+// Rayas verticales que aparecen y desaparecen
+pub struct Scratches {
+    pub enabled: bool,
+    pub count: usize,  // Máximo de rayas por cuadro
+    pub opacity: f32,
+}
+
+// Corre el rojo y el azul en direcciones opuestas, como una copia mal alineada
+pub struct Bleed {
+    pub enabled: bool,
+    pub offset: usize, // Desplazamiento en pixeles
+}
+
+pub struct PostFx {
+    pub enabled: bool,
+    pub grain: Grain,
+    pub sepia: Sepia,
+    pub vignette: Vignette,
+    pub flicker: Flicker,
+    pub scratches: Scratches,
+    pub bleed: Bleed,
+    vignette_table: Vec<u16>,         // Brillo de 0 a 256 para cada pixel
+    vignette_key: Option<(u32, u32)>, // Parámetros con los que se calculó la tabla
+    noise: Vec<i8>,                   // Ruido precalculado; cada cuadro lo lee desde otro punto
+    seed: u32,
+    row: Vec<u32>,
+}
+
+const NOISE_SIZE: usize = 1 << 16;
+const ALPHA: u32 = 0xFF000000;
+
+// Multiplica los tres canales por `factor`/256, con rojo y azul juntos en una operación.
+// Como todos los efectos, conserva el alfa del pixel
+fn scale(pixel: u32, factor: u32) -> u32 {
+    (pixel & ALPHA) | ((((pixel & 0xFF00FF) * factor) >> 8) & 0xFF00FF) | ((((pixel & 0x00FF00) * factor) >> 8) & 0x00FF00)
+}
+
+// Toma el rojo de `offset` pixeles a la izquierda y el azul de `offset` a la derecha
+fn bleed_row(line: &mut [u32], source: &mut [u32], offset: usize) {
+    source.copy_from_slice(line);
+    let width = line.len();
+    if width <= 2 * offset {
+        return;
+    }
+
+    let inner = line[offset..width - offset].iter_mut().zip(&source[..width - 2 * offset]).zip(&source[2 * offset..]);
+    for ((pixel, &left), &right) in inner {
+        *pixel = (left & 0xFF0000) | (*pixel & (ALPHA | 0x00FF00)) | (right & 0x0000FF);
+    }
+}
+
+fn sepia_row(line: &mut [u32], amount: u32) {
+    let amount = amount as i32;
+    for pixel in line.iter_mut() {
+        let (r, g, b) = (((*pixel >> 16) & 0xFF) as i32, ((*pixel >> 8) & 0xFF) as i32, (*pixel & 0xFF) as i32);
+        let luma = (r * 77 + g * 150 + b * 29) >> 8;
+        let (sr, sg, sb) = (((luma * 280) >> 8).min(255), (luma * 230) >> 8, (luma * 170) >> 8);
+        let r = r + (((sr - r) * amount) >> 8);
+        let g = g + (((sg - g) * amount) >> 8);
+        let b = b + (((sb - b) * amount) >> 8);
+        *pixel = (*pixel & ALPHA) | ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
+    }
+}
+
+fn vignette_row(line: &mut [u32], vignette: &[u16], flicker: u32) {
+    for (pixel, &factor) in line.iter_mut().zip(vignette) {
+        *pixel = scale(*pixel, (factor as u32 * flicker) >> 8);
+    }
+}
+
+// Suma el mismo ruido a los tres canales, así el grano es gris como en la película
+fn grain_row(line: &mut [u32], noise: &[i8], strength: i32) {
+    for (pixel, &n) in line.iter_mut().zip(noise) {
+        let n = (n as i32 * strength) >> 7;
+        let channel = |shift: u32| ((((*pixel >> shift) & 0xFF) as i32 + n).clamp(0, 255) as u32) << shift;
+        *pixel = (*pixel & ALPHA) | channel(16) | channel(8) | channel(0);
+    }
+}
+
+impl PostFx {
+    pub fn new() -> Self {
+        // Empieza apagado para no cambiar el aspecto del juego; V o F1 a F6 lo encienden
+        let mut postfx = PostFx {
+            enabled: false,
+            grain: Grain { enabled: true, strength: 28 },
+            sepia: Sepia { enabled: true, amount: 0.7 },
+            vignette: Vignette { enabled: true, strength: 0.6, radius: 0.45 },
+            flicker: Flicker { enabled: true, amount: 0.08 },
+            scratches: Scratches { enabled: true, count: 3, opacity: 0.35 },
+            bleed: Bleed { enabled: true, offset: 2 },
+            vignette_table: Vec::new(),
+            vignette_key: None,
+            noise: Vec::new(),
+            seed: 0x1930_CAFE,
+            row: Vec::new(),
+        };
+        postfx.noise = (0..NOISE_SIZE).map(|_| postfx.random() as i8).collect();
+        postfx
+    }
+
+    // Nombre y bandera de cada efecto, en el orden en que se aplican
+    pub fn effects(&mut self) -> [(&'static str, &mut bool); 6] {
+        [
+            ("Chromatic bleed", &mut self.bleed.enabled),
+            ("Sepia", &mut self.sepia.enabled),
+            ("Vignette", &mut self.vignette.enabled),
+            ("Flicker", &mut self.flicker.enabled),
+            ("Film grain", &mut self.grain.enabled),
+            ("Scratches", &mut self.scratches.enabled),
+        ]
+    }
+
+    // Generador xorshift: barato y suficiente para ruido visual
+    fn random(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+
+    // La viñeta sólo depende del tamaño y de sus parámetros, así que se calcula una vez
+    fn update_vignette(&mut self, width: usize, height: usize) {
+        let key = (self.vignette.strength.to_bits(), self.vignette.radius.to_bits());
+        if self.vignette_key == Some(key) && self.vignette_table.len() == width * height {
+            return;
+        }
+        self.vignette_key = Some(key);
+
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let corner = (cx * cx + cy * cy).sqrt();
+        let radius = self.vignette.radius.clamp(0.0, 0.99);
+
+        self.vignette_table = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32 + 0.5 - cx, (i / width) as f32 + 0.5 - cy);
+                let t = (((x * x + y * y).sqrt() / corner - radius) / (1.0 - radius)).clamp(0.0, 1.0);
+                let falloff = t * t * (3.0 - 2.0 * t);
+                ((1.0 - self.vignette.strength * falloff) * 256.0) as u16
+            })
+            .collect();
+    }
+
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer) {
+        if !self.enabled {
+            return;
+        }
+        let (width, height) = (framebuffer.width, framebuffer.height);
+
+        if self.vignette.enabled {
+            self.update_vignette(width, height);
+        }
+        let flicker = if self.flicker.enabled {
+            let loss = (self.random() & 0xFF) as f32 / 255.0 * self.flicker.amount;
+            ((1.0 - loss) * 256.0) as u32
+        } else {
+            256
+        };
+        let sepia = if self.sepia.enabled { (self.sepia.amount.clamp(0.0, 1.0) * 256.0) as u32 } else { 0 };
+        let grain = if self.grain.enabled { self.grain.strength.min(255) as i32 } else { 0 };
+        let bleed = if self.bleed.enabled { self.bleed.offset.min(width - 1) } else { 0 };
+        let noise_start = self.random() as usize;
+
+        if self.vignette.enabled || flicker < 256 || sepia > 0 || grain > 0 || bleed > 0 {
+            self.filter_pixels(framebuffer, flicker, sepia, grain, bleed, noise_start);
+        }
+        if self.scratches.enabled {
+            self.draw_scratches(framebuffer);
+        }
+    }
+
+    // Pasada por filas: cada fila queda en caché mientras le pasan todos los efectos,
+    // y cada efecto es un ciclo simple que el compilador puede vectorizar
+    fn filter_pixels(&mut self, framebuffer: &mut Framebuffer, flicker: u32, sepia: u32, grain: i32, bleed: usize, noise_start: usize) {
+        let width = framebuffer.width;
+        let noise_span = NOISE_SIZE.saturating_sub(width).max(1);
+        self.row.resize(width, 0);
+
+        for (y, line) in framebuffer.buffer.chunks_exact_mut(width).enumerate() {
+            if bleed > 0 {
+                bleed_row(line, &mut self.row, bleed);
+            }
+            if sepia > 0 {
+                sepia_row(line, sepia);
+            }
+            if self.vignette.enabled {
+                vignette_row(line, &self.vignette_table[y * width..(y + 1) * width], flicker);
+            } else if flicker < 256 {
+                line.iter_mut().for_each(|pixel| *pixel = scale(*pixel, flicker));
+            }
+            if grain > 0 {
+                let start = (noise_start + y * width) % noise_span;
+                grain_row(line, &self.noise[start..], grain);
+            }
+        }
+    }
+
+    // Cada raya aparece con probabilidad 1/2, en una columna y un tramo al azar
+    fn draw_scratches(&mut self, framebuffer: &mut Framebuffer) {
+        let (width, height) = (framebuffer.width, framebuffer.height);
+
+        for _ in 0..self.scratches.count {
+            if self.random() & 1 == 0 {
+                continue;
+            }
+            let x = self.random() as usize % width;
+            let top = self.random() as usize % height;
+            let length = height / 4 + self.random() as usize % height;
+            let color: [i32; 3] = if self.random() & 1 == 0 { [0xF0, 0xE8, 0xD0] } else { [0x20, 0x18, 0x10] };
+            let alpha = (self.scratches.opacity.clamp(0.0, 1.0) * 256.0) as i32;
+
+            for y in top..(top + length).min(height) {
+                let pixel = &mut framebuffer.buffer[y * width + x];
+                let channel = |shift: u32, target: i32| {
+                    let current = ((*pixel >> shift) & 0xFF) as i32;
+                    ((current + (((target - current) * alpha) >> 8)) as u32) << shift
+                };
+                *pixel = (*pixel & ALPHA) | channel(16, color[0]) | channel(8, color[1]) | channel(0, color[2]);
+            }
+        }
+    }
+}
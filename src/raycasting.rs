@@ -1,24 +1,75 @@
+use nalgebra::Vector2;
 use crate::player::Player;
+use crate::doors::{self, Doors, DOOR_OFFSET};
+use crate::walls::{self, Panel, WallMap, WallShape};
+use crate::heights::HeightMap;
 
 pub struct RayHit {
     pub distance: f32,      
     pub hit_x: f32,         
     pub hit_y: f32,        
     pub wall_type: char,    
-    pub is_vertical: bool,  
+    pub tx: f32,            // Coordenada horizontal en la textura (0.0..1.0)
+    pub height: f32,        // Altura del borde superior de la pared o plataforma
+    pub exit_distance: f32, // Distancia a la que el rayo sale de la celda, para dibujar la cara superior
 }
 
+// Impacto contra un panel delgado (paredes delgadas y puertas)
+fn panel_hit(origin: &Vector2<f32>, dir: &Vector2<f32>, distance: f32, tx: f32, wall_type: char, height: f32) -> RayHit {
+    RayHit {
+        distance,
+        hit_x: origin.x + distance * dir.x,
+        hit_y: origin.y + distance * dir.y,
+        wall_type,
+        tx,
+        height,
+        exit_distance: distance,
+    }
+}
+
+// Una pared opaca que llega a la altura de los ojos tapa todo lo que está detrás de ella
+// y no asoma por encima
+pub fn blocks_view(hit: &RayHit, camera_z: f32) -> bool {
+    !walls::is_see_through(hit.wall_type) && hit.height >= camera_z
+}
+
+// Pendiente del borde superior de una pared vista desde la cámara: detrás de una pared
+// opaca sólo se ve lo que tenga una pendiente mayor
+fn top_slope(hit: &RayHit, camera_z: f32) -> f32 {
+    (hit.height - camera_z) / hit.distance.max(f32::EPSILON)
+}
+
+// Guarda un impacto salvo que quede escondido detrás de `occluder`, la pendiente de la
+// pared opaca que más tapa hasta ahora, y la actualiza si el impacto tapa más
+fn add_hit(hits: &mut Vec<RayHit>, occluder: &mut Option<f32>, hit: RayHit, camera_z: f32) {
+    let slope = top_slope(&hit, camera_z);
+    if occluder.is_some_and(|clip| slope <= clip) {
+        return;
+    }
+    if blocks_view(&hit, camera_z) {
+        *occluder = Some(occluder.map_or(slope, |clip| clip.max(slope)));
+    }
+    hits.push(hit);
+}
+
+// Devuelve los impactos del rayo ordenados del más cercano al más lejano,
+// terminando en la primera pared opaca que tape todo lo que haya detrás
 pub fn cast_ray(
-    maze: &Vec<Vec<char>>,
+    maze: &[Vec<char>],
+    walls: &WallMap,
+    heights: &HeightMap,
+    doors: &Doors,
     player: &Player,
     angle: f32,
     _block_size: usize,
-) -> RayHit {
+) -> Vec<RayHit> {
     let sin_a = angle.sin();
     let cos_a = angle.cos();
 
     let x = player.pos.x;
     let y = player.pos.y;
+    let origin = Vector2::new(x, y);
+    let dir = Vector2::new(cos_a, sin_a);
 
     let delta_dist_x = (1.0 / cos_a).abs();
     let delta_dist_y = (1.0 / sin_a).abs();
@@ -41,48 +92,85 @@ pub fn cast_ray(
         (y - map_y as f32) * delta_dist_y
     };
 
-    let mut hit = false;
-    let mut wall_type = ' ';
-    let mut is_vertical = false;
-    let mut distance = 0.0;
-    let mut hit_x = 0.0;
-    let mut hit_y = 0.0;
+    let mut hits = Vec::new();
+    let camera_z = heights.camera_z(player);
 
-    while !hit {
-        if side_dist_x < side_dist_y {
+    // El rayo termina cuando ni la pared más alta del mapa podría asomar por encima de
+    // las paredes opacas que ya encontró
+    let mut occluder: Option<f32> = None;
+    let hidden_beyond = |occluder: Option<f32>, distance: f32| {
+        occluder.is_some_and(|clip| (heights.max_wall - camera_z) / distance.max(f32::EPSILON) <= clip)
+    };
+
+    // El jugador puede estar dentro de una celda con paneles delgados
+    if let Some((d, along)) = walls.shape(map_x as usize, map_y as usize).intersect(&origin, &dir, map_x, map_y) {
+        let wall_type = maze[map_y as usize][map_x as usize];
+        add_hit(&mut hits, &mut occluder, panel_hit(&origin, &dir, d, along, wall_type, heights.wall(map_x as usize, map_y as usize)), camera_z);
+    }
+
+    loop {
+        if hidden_beyond(occluder, side_dist_x.min(side_dist_y)) {
+            break;
+        }
+
+        let is_vertical = if side_dist_x < side_dist_y {
             side_dist_x += delta_dist_x;
             map_x += step_x;
-            is_vertical = true;
+            true
         } else {
             side_dist_y += delta_dist_y;
             map_y += step_y;
-            is_vertical = false;
-        }
+            false
+        };
 
-        if map_y < 0 || map_y >= maze.len() as isize || map_x < 0 || map_x >= maze[0].len() as isize {
+        if map_y < 0 || map_y >= maze.len() as isize || map_x < 0 || map_x >= maze[map_y as usize].len() as isize {
             break;
         }
 
-        wall_type = maze[map_y as usize][map_x as usize];
-        if wall_type != ' ' {
-            hit = true;
-            if is_vertical {
-                distance = (map_x as f32 - x + (1.0 - step_x as f32) / 2.0) / cos_a;
-                hit_x = map_x as f32;
-                hit_y = y + distance * sin_a;
-            } else {
-                distance = (map_y as f32 - y + (1.0 - step_y as f32) / 2.0) / sin_a;
-                hit_x = x + distance * cos_a;
-                hit_y = map_y as f32;
+        let wall_type = maze[map_y as usize][map_x as usize];
+        let wall_height = heights.wall(map_x as usize, map_y as usize);
+        let floor_height = heights.floor(map_x as usize, map_y as usize);
+
+        let hit = if doors::is_door(wall_type) {
+            // Las puertas son paneles delgados a mitad de la celda que se deslizan al abrirse
+            doors.get(map_x as usize, map_y as usize).and_then(|door| {
+                let panel = Panel { vertical: door.vertical, offset: DOOR_OFFSET, start: door.open, end: 1.0 };
+                panel
+                    .intersect(&origin, &dir, map_x, map_y)
+                    .map(|(d, along)| panel_hit(&origin, &dir, d, along - door.open, wall_type, wall_height))
+            })
+        } else {
+            match walls.shape(map_x as usize, map_y as usize) {
+                WallShape::Empty if floor_height <= 0.0 => None,
+                // Las celdas vacías con piso elevado se dibujan como bloques bajos
+                WallShape::Empty | WallShape::Block => {
+                    let height = if walls::is_empty(wall_type) { floor_height } else { wall_height };
+                    let (distance, hit_x, hit_y, tx);
+                    if is_vertical {
+                        distance = (map_x as f32 - x + (1.0 - step_x as f32) / 2.0) / cos_a;
+                        hit_x = map_x as f32;
+                        hit_y = y + distance * sin_a;
+                        tx = hit_y % 1.0;
+                    } else {
+                        distance = (map_y as f32 - y + (1.0 - step_y as f32) / 2.0) / sin_a;
+                        hit_x = x + distance * cos_a;
+                        hit_y = map_y as f32;
+                        tx = hit_x % 1.0;
+                    }
+                    let exit_distance = side_dist_x.min(side_dist_y);
+                    Some(RayHit { distance, hit_x, hit_y, wall_type, tx, height, exit_distance })
+                }
+                shape => shape
+                    .intersect(&origin, &dir, map_x, map_y)
+                    .map(|(d, along)| panel_hit(&origin, &dir, d, along, wall_type, wall_height)),
             }
+        };
+
+        // Las paredes que dejan ver a través o que son más bajas no detienen el rayo
+        if let Some(hit) = hit {
+            add_hit(&mut hits, &mut occluder, hit, camera_z);
         }
     }
 
-    RayHit {
-        distance,
-        hit_x,
-        hit_y,
-        wall_type,
-        is_vertical,
-    }
+    hits
 }
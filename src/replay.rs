@@ -0,0 +1,228 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use nalgebra::Vector2;
+use crate::player::Player;
+use crate::weapons::WeaponKind;
+
+// Primera línea de cada archivo, para que se entienda al abrirlo
+const HEADER: &str = "# x y angle pitch camera_height fov bob_phase bob_amount weapon fire interact torch";
+const FIELDS: usize = 12;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io { path: PathBuf, source: std::io::Error },          // No se pudo leer o escribir el archivo
+    Parse { path: PathBuf, line: usize, message: String }, // Una línea no tiene el formato esperado
+    Empty { path: PathBuf },                               // El archivo no tiene ningún cuadro
+}
+
+impl ReplayError {
+    fn io(path: &Path, source: std::io::Error) -> Self {
+        ReplayError::Io { path: path.to_path_buf(), source }
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, source } => write!(f, "{}: could not access replay ({})", path.display(), source),
+            ReplayError::Parse { path, line, message } => write!(f, "{}:{}: invalid replay frame ({})", path.display(), line, message),
+            ReplayError::Empty { path } => write!(f, "{}: replay has no frames", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Un cuadro de una partida grabada: la vista del jugador después de moverse y lo que
+// hizo en ese cuadro
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub pos: Vector2<f32>,
+    pub angle: f32,
+    pub pitch: f32,
+    pub camera_height: f32,
+    pub fov: f32,
+    pub bob_phase: f32,
+    pub bob_amount: f32, // 0.0 con el balanceo apagado
+    pub weapon: WeaponKind,
+    pub fire: bool,      // Espacio apretado
+    pub interact: bool,  // E recién apretada: abre o cierra puertas
+    pub torch: bool,
+}
+
+impl ReplayFrame {
+    pub fn new(player: &Player, weapon: WeaponKind, fire: bool, interact: bool, torch: bool) -> Self {
+        ReplayFrame {
+            pos: player.pos,
+            angle: player.a,
+            pitch: player.pitch,
+            camera_height: player.camera_height,
+            fov: player.fov,
+            bob_phase: player.bob_phase,
+            bob_amount: if player.head_bob { player.bob_amount } else { 0.0 },
+            weapon,
+            fire,
+            interact,
+            torch,
+        }
+    }
+
+    // Pone al jugador donde estaba y mirando hacia donde miraba en este cuadro
+    pub fn apply(&self, player: &mut Player) {
+        player.pos = self.pos;
+        player.a = self.angle;
+        player.pitch = self.pitch;
+        player.camera_height = self.camera_height;
+        player.fov = self.fov;
+        player.head_bob = true;
+        player.bob_phase = self.bob_phase;
+        player.bob_amount = self.bob_amount;
+    }
+
+    // Los f32 se escriben con la representación más corta que vuelve al mismo valor, así
+    // la repetición no se aparta de lo que se jugó
+    fn to_line(self) -> String {
+        let weapon = WeaponKind::ALL.iter().position(|&kind| kind == self.weapon).unwrap_or(0);
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {}",
+            self.pos.x, self.pos.y, self.angle, self.pitch, self.camera_height, self.fov,
+            self.bob_phase, self.bob_amount, weapon, self.fire as u8, self.interact as u8, self.torch as u8
+        )
+    }
+
+    fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != FIELDS {
+            return Err(format!("expected {} fields, found {}", FIELDS, fields.len()));
+        }
+        let number = |i: usize| fields[i].parse::<f32>().map_err(|_| format!("field {} is not a number: {}", i + 1, fields[i]));
+        let flag = |i: usize| match fields[i] {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            other => Err(format!("field {} is not 0 or 1: {}", i + 1, other)),
+        };
+        let weapon = fields[8]
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| WeaponKind::ALL.get(index).copied())
+            .ok_or_else(|| format!("unknown weapon: {}", fields[8]))?;
+
+        Ok(ReplayFrame {
+            pos: Vector2::new(number(0)?, number(1)?),
+            angle: number(2)?,
+            pitch: number(3)?,
+            camera_height: number(4)?,
+            fov: number(5)?,
+            bob_phase: number(6)?,
+            bob_amount: number(7)?,
+            weapon,
+            fire: flag(9)?,
+            interact: flag(10)?,
+            torch: flag(11)?,
+        })
+    }
+}
+
+// Escribe la partida cuadro por cuadro mientras se juega
+pub struct ReplayWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    frames: usize,
+}
+
+impl ReplayWriter {
+    pub fn create(path: PathBuf) -> Result<Self, ReplayError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| ReplayError::io(parent, e))?;
+        }
+        let file = File::create(&path).map_err(|e| ReplayError::io(&path, e))?;
+        let mut writer = ReplayWriter { path, file: BufWriter::new(file), frames: 0 };
+        writeln!(writer.file, "{}", HEADER).map_err(|e| ReplayError::io(&writer.path, e))?;
+        Ok(writer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn push(&mut self, frame: ReplayFrame) -> Result<(), ReplayError> {
+        writeln!(self.file, "{}", frame.to_line()).map_err(|e| ReplayError::io(&self.path, e))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    // Termina de escribir y devuelve la ruta y la cantidad de cuadros
+    pub fn finish(mut self) -> Result<(PathBuf, usize), ReplayError> {
+        self.file.flush().map_err(|e| ReplayError::io(&self.path, e))?;
+        Ok((self.path, self.frames))
+    }
+}
+
+// Lee una partida grabada. Las líneas vacías y las que empiezan con # se ignoran
+pub fn load(path: &Path) -> Result<Vec<ReplayFrame>, ReplayError> {
+    let text = fs::read_to_string(path).map_err(|e| ReplayError::io(path, e))?;
+    parse(&text, path)
+}
+
+fn parse(text: &str, path: &Path) -> Result<Vec<ReplayFrame>, ReplayError> {
+    let frames = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            ReplayFrame::parse(line).map_err(|message| ReplayError::Parse { path: path.to_path_buf(), line: i + 1, message })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if frames.is_empty() {
+        return Err(ReplayError::Empty { path: path.to_path_buf() });
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> ReplayFrame {
+        let mut player = Player::new(Vector2::new(1.5, 2.25), 0.1);
+        player.pitch = -12.5;
+        player.bob_phase = 3.7;
+        player.bob_amount = 0.8;
+        ReplayFrame::new(&player, WeaponKind::Spread, true, false, true)
+    }
+
+    #[test]
+    fn frames_survive_a_round_trip() {
+        let text = format!("{}\n{}\n\n{}\n", HEADER, frame().to_line(), frame().to_line());
+        let frames = parse(&text, Path::new("test.replay")).unwrap();
+        assert_eq!(frames, vec![frame(), frame()]);
+    }
+
+    #[test]
+    fn bob_is_off_when_head_bob_is_disabled() {
+        let mut player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        player.bob_amount = 1.0;
+        player.head_bob = false;
+        ReplayFrame::new(&player, WeaponKind::Peashooter, false, false, false).apply(&mut player);
+        assert_eq!(player.bob_offset(), 0.0);
+    }
+
+    #[test]
+    fn bad_lines_report_their_line_number() {
+        let text = format!("{}\n{}\n1 2 3\n", HEADER, frame().to_line());
+        match parse(&text, Path::new("test.replay")) {
+            Err(ReplayError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("expected a parse error, got {:?}", other.map(|frames| frames.len())),
+        }
+        assert!(matches!(parse(HEADER, Path::new("test.replay")), Err(ReplayError::Empty { .. })));
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use nalgebra::Vector2;
+use crate::textures::Texture;
+
+// Cómo se apoya un sprite en el mundo
+#[derive(Clone, Copy)]
+pub enum Anchor {
+    Floor,                              // Parado sobre el piso de su celda
+    Ceiling(f32),                       // Colgado: el borde de arriba queda a esta altura
+    Floating { height: f32, bob: f32 }, // A `height` sobre el piso, subiendo y bajando `bob`
+    Fixed(f32),                         // Centrado a esta altura, sin importar el piso
+}
+
+// Tamaño del sprite en el mundo. El ancho sale de la proporción de la textura, así una
+// imagen angosta no se estira; `width_scale` lo ajusta si hace falta
+#[derive(Clone, Copy)]
+pub struct SpriteShape {
+    pub height: f32,
+    pub width_scale: f32,
+    pub anchor: Anchor,
+}
+
+const BOB_SPEED: f32 = 2.5; // Radianes por segundo del vaivén de los sprites que flotan
+
+impl SpriteShape {
+    // Alturas de la base y del borde de arriba, sobre un piso a `floor` y en el instante
+    // `time`. `phase` desfasa el vaivén para que los sprites no floten todos a la vez
+    pub fn extent(&self, floor: f32, time: f32, phase: f32) -> (f32, f32) {
+        let bottom = match self.anchor {
+            Anchor::Floor => floor,
+            Anchor::Ceiling(top) => top - self.height,
+            Anchor::Floating { height, bob } => floor + height + bob * (time * BOB_SPEED + phase).sin(),
+            Anchor::Fixed(center) => center - self.height / 2.0,
+        };
+        (bottom, bottom + self.height)
+    }
+
+    pub fn aspect(&self, texture: &Texture) -> f32 {
+        texture.width as f32 / texture.height.max(1) as f32 * self.width_scale
+    }
+}
+
+// Lo que se dibuja de una entidad en un cuadro
+pub struct Sprite {
+    pub pos: Vector2<f32>,
+    pub texture: Arc<Texture>,
+    pub shape: SpriteShape,
+    pub phase: f32,
+}
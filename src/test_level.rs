@@ -0,0 +1,8 @@
+use crate::maze;
+use crate::world::World;
+
+// Nivel armado a partir de filas de texto, con paredes gruesas y sin mapa de alturas,
+// para las pruebas
+pub fn level(rows: &[&str]) -> World {
+    World::new(maze::from_rows(rows), "", false)
+}
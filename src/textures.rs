@@ -1,22 +1,78 @@
 extern crate image;
 
-use image::{ImageReader, GenericImageView};
+use image::{ImageReader, GenericImageView, Rgba};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::assets::{self, AssetError};
 
+// Filtrado de texturas, se cambia en tiempo de ejecución
+static BILINEAR: AtomicBool = AtomicBool::new(false);
+static MIPMAPS: AtomicBool = AtomicBool::new(false);
+
+// Los texels se guardan por columnas en un solo buffer: el texel (x, y) está en
+// x * height + y, así el bucle de paredes recorre memoria contigua
 pub struct Texture {
     pub width: u32,
     pub height: u32,
-    pub color_array: Vec<Vec<u32>>,
+    pixels: Vec<u32>,
+    power_of_two: bool, // Con ancho y alto potencia de dos, el módulo se vuelve una máscara
+    mips: Vec<Texture>, // Niveles reducidos, cada uno de la mitad del tamaño del anterior
+}
+
+pub fn set_bilinear(enabled: bool) {
+    BILINEAR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn bilinear() -> bool {
+    BILINEAR.load(Ordering::Relaxed)
+}
+
+pub fn set_mipmaps(enabled: bool) {
+    MIPMAPS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn mipmaps() -> bool {
+    MIPMAPS.load(Ordering::Relaxed)
+}
+
+// Interpola cada canal ARGB de dos colores
+fn lerp_color(a: u32, b: u32, t: f32) -> u32 {
+    let mix = |shift: u32| {
+        let ca = ((a >> shift) & 0xFF) as f32;
+        let cb = ((b >> shift) & 0xFF) as f32;
+        ((ca + (cb - ca) * t) as u32) << shift
+    };
+    mix(24) | mix(16) | mix(8) | mix(0)
+}
+
+// Mezcla dos colores RGB, t = 0.0 devuelve a y t = 1.0 devuelve b
+pub fn blend(a: u32, b: u32, t: f32) -> u32 {
+    lerp_color(a, b, t) & 0xFFFFFF
+}
+
+// Promedio de cuatro colores ARGB, para reducir un bloque de 2x2 texels
+fn average(colors: [u32; 4]) -> u32 {
+    let channel = |shift: u32| (colors.iter().map(|c| (c >> shift) & 0xFF).sum::<u32>() / 4) << shift;
+    channel(24) | channel(16) | channel(8) | channel(0)
 }
 
 impl Texture {
-    pub fn new(file_path: &str) -> Texture {
-        let img = ImageReader::open(file_path).unwrap().decode().unwrap();
+    pub fn load(file_path: &str) -> Result<Texture, AssetError> {
+        let img = ImageReader::open(file_path)
+            .map_err(|e| AssetError::io(file_path, e))?
+            .decode()
+            .map_err(|e| AssetError::decode(file_path, e))?;
+        Ok(Texture::from_image(&img))
+    }
+
+    // Convierte una imagen ya decodificada, por ejemplo un cuadro de un GIF
+    pub fn from_image(img: &impl GenericImageView<Pixel = Rgba<u8>>) -> Texture {
         let width = img.width();
         let height = img.height();
-        let mut color_array = vec![vec![0; height as usize]; width as usize];
+        let mut pixels = vec![0; (width * height) as usize];
 
         for x in 0..width {
             for y in 0..height {
+                let index = (x * height + y) as usize;
                 let pixel = img.get_pixel(x, y).0;
                 let r = pixel[0];
                 let g = pixel[1];
@@ -24,19 +80,143 @@ impl Texture {
                 let a = pixel[3];  
 
                 if a == 0 {
-                    color_array[x as usize][y as usize] = 0; 
+                    pixels[index] = 0; 
                 } else {
                     let color = ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
-                    color_array[x as usize][y as usize] = color;
+                    pixels[index] = color;
                 }
             }
         }
 
-        Texture { width, height, color_array }
+        Texture::with_mips(width, height, pixels)
+    }
+
+    // Tablero de "textura faltante" para cuando una imagen no carga
+    pub fn missing() -> Texture {
+        Texture::from_fn(64, 64, assets::checker)
+    }
+
+    // Textura generada por código, el color de cada texel en formato ARGB
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> u32) -> Texture {
+        let pixels = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+
+        Texture::with_mips(width, height, pixels)
+    }
+
+    fn from_pixels(width: u32, height: u32, pixels: Vec<u32>) -> Texture {
+        Texture {
+            width,
+            height,
+            pixels,
+            power_of_two: width.is_power_of_two() && height.is_power_of_two(),
+            mips: Vec::new(),
+        }
+    }
+
+    // Genera la cadena de mipmaps al cargar, promediando bloques de 2x2 hasta llegar a 1x1
+    fn with_mips(width: u32, height: u32, pixels: Vec<u32>) -> Texture {
+        let mut texture = Texture::from_pixels(width, height, pixels);
+        let mut previous = &texture;
+        let mut mips = Vec::new();
+
+        while previous.width > 1 || previous.height > 1 {
+            let (w, h) = (previous.width, previous.height);
+            let next_w = (w / 2).max(1);
+            let next_h = (h / 2).max(1);
+            let level = (0..next_w)
+                .flat_map(|x| (0..next_h).map(move |y| (x, y)))
+                .map(|(x, y)| {
+                    let x0 = (x * 2).min(w - 1);
+                    let x1 = (x * 2 + 1).min(w - 1);
+                    let y0 = (y * 2).min(h - 1);
+                    let y1 = (y * 2 + 1).min(h - 1);
+                    average([
+                        previous.get_pixel_color(x0, y0),
+                        previous.get_pixel_color(x1, y0),
+                        previous.get_pixel_color(x0, y1),
+                        previous.get_pixel_color(x1, y1),
+                    ])
+                })
+                .collect();
+
+            mips.push(Texture::from_pixels(next_w, next_h, level));
+            previous = mips.last().unwrap();
+        }
+
+        texture.mips = mips;
+        texture
+    }
+
+    fn wrap_x(&self, x: u32) -> usize {
+        if self.power_of_two {
+            (x & (self.width - 1)) as usize
+        } else {
+            (x % self.width) as usize
+        }
+    }
+
+    // Fila dentro de una columna, repitiendo la textura verticalmente
+    pub fn wrap_y(&self, y: u32) -> usize {
+        if self.power_of_two {
+            (y & (self.height - 1)) as usize
+        } else {
+            (y % self.height) as usize
+        }
     }
 
     pub fn get_pixel_color(&self, x: u32, y: u32) -> u32 {
-        self.color_array[x as usize % self.width as usize][y as usize % self.height as usize]
+        self.pixels[self.wrap_x(x) * self.height as usize + self.wrap_y(y)]
     }
-}
 
+    // Columna completa de texels, para recorrer una pared sin recalcular el índice de x
+    pub fn column(&self, x: u32) -> &[u32] {
+        let start = self.wrap_x(x) * self.height as usize;
+        &self.pixels[start..start + self.height as usize]
+    }
+
+    // Interpolación bilineal entre los cuatro texels más cercanos, repitiendo la textura
+    fn get_bilinear_color(&self, u: f32, v: f32) -> u32 {
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let texel = |tx: f32, ty: f32| {
+            let tx = (tx as i64).rem_euclid(self.width as i64) as usize;
+            let ty = (ty as i64).rem_euclid(self.height as i64) as usize;
+            self.pixels[tx * self.height as usize + ty]
+        };
+
+        let top = lerp_color(texel(x0, y0), texel(x0 + 1.0, y0), fx);
+        let bottom = lerp_color(texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0), fx);
+        lerp_color(top, bottom, fy)
+    }
+
+    // Nivel de mipmap adecuado para la densidad de texels, o la textura completa sin mipmaps
+    pub fn mip(&self, texels_per_pixel: f32) -> &Texture {
+        if !mipmaps() || texels_per_pixel <= 1.0 {
+            return self;
+        }
+        let level = (texels_per_pixel.log2().floor() as usize).min(self.mips.len());
+        if level == 0 { self } else { &self.mips[level - 1] }
+    }
+
+    // Muestrea la textura en coordenadas normalizadas (se repite fuera de 0.0..1.0).
+    // `texels_per_pixel` es cuántos texels caen en un pixel de pantalla y decide el mipmap
+    pub fn sample(&self, u: f32, v: f32, texels_per_pixel: f32) -> u32 {
+        let texture = self.mip(texels_per_pixel);
+
+        if bilinear() {
+            texture.get_bilinear_color(u, v)
+        } else {
+            let u = u.rem_euclid(1.0);
+            let v = v.rem_euclid(1.0);
+            texture.get_pixel_color((u * texture.width as f32) as u32, (v * texture.height as f32) as u32)
+        }
+    }
+}
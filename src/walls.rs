@@ -0,0 +1,166 @@
+use nalgebra::Vector2;
+use crate::doors;
+use crate::items;
+use crate::checkpoints;
+use crate::lighting;
+use crate::intersect::intersect_panel;
+
+const MOVE_MARGIN: f32 = 0.15; // Distancia mínima entre el jugador y un panel delgado
+
+// Panel delgado dentro de una celda. `offset` es su posición en la celda y
+// `start..end` el tramo que ocupa a lo largo del panel (todo en 0.0..1.0).
+#[derive(Clone, Copy)]
+pub struct Panel {
+    pub vertical: bool,
+    pub offset: f32,
+    pub start: f32,
+    pub end: f32,
+}
+
+pub enum WallShape {
+    Empty,
+    Block,
+    Panels(Vec<Panel>),
+}
+
+pub struct WallMap {
+    shapes: Vec<Vec<WallShape>>,
+}
+
+// Paredes delgadas del mapa: (celda, vertical, offset)
+const THIN_WALLS: &[(char, bool, f32)] = &[
+    ('=', false, 0.5), // Valla horizontal a la mitad de la celda
+    ('!', true, 0.5),  // Valla vertical a la mitad de la celda
+    ('[', true, 0.1),  // Panel vertical pegado al lado oeste
+    (']', true, 0.9),  // Panel vertical pegado al lado este
+    ('^', false, 0.1), // Panel horizontal pegado al lado norte
+    ('_', false, 0.9), // Panel horizontal pegado al lado sur
+];
+
+// Rejas ('#'), vidrio ('%') y vallas ('=', '!') tienen texturas con huecos o
+// translúcidas, así que el rayo sigue de largo después de tocarlas
+pub fn is_see_through(cell: char) -> bool {
+    matches!(cell, '#' | '%' | '=' | '!')
+}
+
+impl Panel {
+    pub fn full(vertical: bool, offset: f32) -> Self {
+        Panel { vertical, offset, start: 0.0, end: 1.0 }
+    }
+
+    // Distancia y coordenada a lo largo del panel donde lo toca el rayo
+    pub fn intersect(&self, origin: &Vector2<f32>, dir: &Vector2<f32>, map_x: isize, map_y: isize) -> Option<(f32, f32)> {
+        intersect_panel(origin, dir, map_x, map_y, self.vertical, self.offset)
+            .filter(|&(_, along)| along >= self.start && along <= self.end)
+    }
+}
+
+impl WallShape {
+    // Impacto más cercano del rayo con los paneles de la celda: (distancia, u)
+    pub fn intersect(&self, origin: &Vector2<f32>, dir: &Vector2<f32>, map_x: isize, map_y: isize) -> Option<(f32, f32)> {
+        match self {
+            WallShape::Panels(panels) => panels
+                .iter()
+                .filter_map(|panel| panel.intersect(origin, dir, map_x, map_y))
+                .min_by(|a, b| a.0.total_cmp(&b.0)),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_empty(cell: char) -> bool {
+    cell == ' ' || items::is_item(cell) || lighting::is_light(cell) || checkpoints::is_checkpoint(cell)
+}
+
+fn cell_at(maze: &[Vec<char>], x: isize, y: isize) -> char {
+    if x < 0 || y < 0 || y as usize >= maze.len() || x as usize >= maze[y as usize].len() {
+        return ' ';
+    }
+    maze[y as usize][x as usize]
+}
+
+// En modo delgado cada poste '+' sólo tiene brazos hacia las paredes vecinas
+fn post_panels(maze: &[Vec<char>], x: isize, y: isize) -> Vec<Panel> {
+    let wall = |dx: isize, dy: isize| !is_empty(cell_at(maze, x + dx, y + dy));
+    let arm = |low: bool, high: bool| {
+        (if low { 0.0 } else { 0.4 }, if high { 1.0 } else { 0.6 })
+    };
+
+    let (start, end) = arm(wall(-1, 0), wall(1, 0));
+    let horizontal = Panel { vertical: false, offset: 0.5, start, end };
+    let (start, end) = arm(wall(0, -1), wall(0, 1));
+    let vertical = Panel { vertical: true, offset: 0.5, start, end };
+
+    vec![horizontal, vertical]
+}
+
+fn cell_shape(maze: &[Vec<char>], x: usize, y: usize, thin_maze: bool) -> WallShape {
+    let cell = maze[y][x];
+
+    if is_empty(cell) || doors::is_door(cell) {
+        return WallShape::Empty;
+    }
+
+    if let Some(&(_, vertical, offset)) = THIN_WALLS.iter().find(|(c, _, _)| *c == cell) {
+        return WallShape::Panels(vec![Panel::full(vertical, offset)]);
+    }
+
+    // Laberintos con formato +--+ dibujados como paredes delgadas entre celdas
+    if thin_maze {
+        match cell {
+            '-' => return WallShape::Panels(vec![Panel::full(false, 0.5)]),
+            '|' => return WallShape::Panels(vec![Panel::full(true, 0.5)]),
+            '+' => return WallShape::Panels(post_panels(maze, x as isize, y as isize)),
+            _ => {}
+        }
+    }
+
+    WallShape::Block
+}
+
+impl WallMap {
+    pub fn from_maze(maze: &[Vec<char>], thin_maze: bool) -> Self {
+        let shapes = maze
+            .iter()
+            .enumerate()
+            .map(|(y, line)| (0..line.len()).map(|x| cell_shape(maze, x, y, thin_maze)).collect())
+            .collect();
+
+        WallMap { shapes }
+    }
+
+    pub fn shape(&self, x: usize, y: usize) -> &WallShape {
+        self.shapes
+            .get(y)
+            .and_then(|line| line.get(x))
+            .unwrap_or(&WallShape::Block)
+    }
+
+    // Comprueba si moverse de `from` a `to` atraviesa o roza algún panel delgado
+    pub fn blocks_move(&self, from: &Vector2<f32>, to: &Vector2<f32>) -> bool {
+        let delta = to - from;
+        let length = delta.norm();
+        if length == 0.0 {
+            return false;
+        }
+        let dir = delta / length;
+
+        let to_x = to.x.floor() as isize;
+        let to_y = to.y.floor() as isize;
+        if to_x < 0 || to_y < 0 {
+            return true;
+        }
+        if let WallShape::Block = self.shape(to_x as usize, to_y as usize) {
+            return true;
+        }
+
+        let from_x = from.x.floor() as isize;
+        let from_y = from.y.floor() as isize;
+
+        [(from_x, from_y), (to_x, to_y)].iter().any(|&(map_x, map_y)| {
+            self.shape(map_x as usize, map_y as usize)
+                .intersect(from, &dir, map_x, map_y)
+                .is_some_and(|(distance, _)| distance < length + MOVE_MARGIN)
+        })
+    }
+}
@@ -0,0 +1,460 @@
+use std::f32::consts::TAU;
+use nalgebra::Vector2;
+use once_cell::sync::Lazy;
+use crate::animation::{Animation, LoopMode};
+use crate::assets::{self, AnimationHandle};
+use crate::doors::{self, DOOR_OFFSET};
+use crate::enemies::Enemies;
+use crate::player::Player;
+use crate::sprites::{Anchor, Sprite, SpriteShape};
+use crate::textures::{self, Texture};
+use crate::walls::{Panel, WallShape};
+use crate::world::World;
+
+const MUZZLE_OFFSET: f32 = 0.3; // Los disparos salen un poco delante del jugador
+const MUZZLE_DROP: f32 = 0.1;   // y un poco por debajo de los ojos, a la altura del dedo
+const WALL_MARGIN: f32 = 0.05;  // El impacto se dibuja apenas antes de la pared
+const SHOT_FRAMES: usize = 4;
+const HIT_FRAMES: usize = 5;
+const HIT_SIZE: f32 = 0.35;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeaponKind {
+    Peashooter, // Tiro rápido y sin límite
+    Spread,     // Un abanico de balas cortas
+    Charge,     // Lento y con pocas balas, pero pega fuerte
+}
+
+// Parámetros de cada arma
+pub struct WeaponStats {
+    pub name: &'static str,
+    cooldown: f32,         // Segundos entre disparos
+    speed: f32,            // Celdas por segundo
+    range: f32,            // Celdas que recorre antes de desvanecerse
+    damage: u32,
+    pellets: usize,        // Balas por disparo
+    spread: f32,           // Radianes entre balas del mismo disparo
+    pub max_ammo: Option<u32>, // None si no se acaban
+    size: f32,             // Alto del sprite en el mundo
+    color: u32,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 3] = [WeaponKind::Peashooter, WeaponKind::Spread, WeaponKind::Charge];
+
+    pub fn stats(self) -> &'static WeaponStats {
+        match self {
+            WeaponKind::Peashooter => &WeaponStats { name: "Peashooter", cooldown: 0.18, speed: 9.0, range: 8.0, damage: 1, pellets: 1, spread: 0.0, max_ammo: None, size: 0.1, color: 0x9CE0FF },
+            WeaponKind::Spread => &WeaponStats { name: "Spread", cooldown: 0.5, speed: 7.0, range: 3.5, damage: 1, pellets: 5, spread: 0.15, max_ammo: Some(40), size: 0.08, color: 0xFF8CC8 },
+            WeaponKind::Charge => &WeaponStats { name: "Charge", cooldown: 1.0, speed: 6.0, range: 10.0, damage: 4, pellets: 1, spread: 0.0, max_ammo: Some(8), size: 0.22, color: 0xFFB040 },
+        }
+    }
+}
+
+// Bola brillante que late: un núcleo blanco y un halo del color del arma
+fn shot_animation(kind: WeaponKind) -> AnimationHandle {
+    let color = kind.stats().color;
+    let frames = (0..SHOT_FRAMES)
+        .map(|frame| {
+            let pulse = (frame as f32 / SHOT_FRAMES as f32 * TAU).sin();
+            let halo = 13.0 + pulse * 2.0;
+            Texture::from_fn(32, 32, move |x, y| {
+                let r = ((x as f32 - 15.5).powi(2) + (y as f32 - 15.5).powi(2)).sqrt();
+                if r < 6.0 {
+                    0xFFFFFFFF
+                } else if r < halo {
+                    0xFF000000 | textures::blend(0xFFFFFF, color, (r - 6.0) / (halo - 6.0))
+                } else {
+                    0x00000000
+                }
+            })
+        })
+        .collect();
+    assets::insert_animation(&format!("shot_{}", kind.stats().name.to_lowercase()), Animation::from_frames(frames, 0.06, LoopMode::Loop))
+}
+
+static SHOTS: Lazy<Vec<AnimationHandle>> = Lazy::new(|| WeaponKind::ALL.iter().map(|&kind| shot_animation(kind)).collect());
+// Estallido del impacto: una estrella de rayos que se abre y se desvanece
+static HIT: Lazy<AnimationHandle> = Lazy::new(|| {
+    let frames = (0..HIT_FRAMES)
+        .map(|frame| {
+            let t = (frame + 1) as f32 / HIT_FRAMES as f32;
+            Texture::from_fn(32, 32, move |x, y| {
+                let (dx, dy) = (x as f32 - 15.5, y as f32 - 15.5);
+                let r = (dx * dx + dy * dy).sqrt();
+                let rays = (dy.atan2(dx) * 4.0).cos().max(0.0);
+                let reach = 15.0 * t * (0.5 + 0.5 * rays);
+                if r < reach && r > reach * t * 0.6 {
+                    0xFF000000 | textures::blend(0xFFF0A0, 0xC86420, t)
+                } else {
+                    0x00000000
+                }
+            })
+        })
+        .collect();
+    assets::insert_animation("shot_hit", Animation::from_frames(frames, 0.05, LoopMode::Once))
+});
+
+// Carga las animaciones de los disparos al iniciar, así el reporte de assets las incluye
+pub fn preload() {
+    Lazy::force(&SHOTS);
+    Lazy::force(&HIT);
+}
+
+// El arma elegida, las balas que le quedan a cada una y la espera hasta el próximo disparo
+#[derive(Clone)]
+pub struct Weapons {
+    pub current: WeaponKind,
+    ammo: Vec<u32>, // En el orden de WeaponKind::ALL; no cuenta para las que no se acaban
+    cooldown: f32,
+}
+
+impl Weapons {
+    pub fn new() -> Self {
+        let ammo = WeaponKind::ALL.iter().map(|kind| kind.stats().max_ammo.unwrap_or(0)).collect();
+        Weapons { current: WeaponKind::Peashooter, ammo, cooldown: 0.0 }
+    }
+
+    // Balas del arma elegida; None si no se acaban
+    pub fn ammo(&self) -> Option<u32> {
+        self.current.stats().max_ammo.map(|_| self.ammo[self.current as usize])
+    }
+
+    // Llena las balas de todas las armas
+    pub fn refill(&mut self) {
+        for (ammo, kind) in self.ammo.iter_mut().zip(WeaponKind::ALL) {
+            *ammo = kind.stats().max_ammo.unwrap_or(0);
+        }
+    }
+
+    pub fn select(&mut self, kind: WeaponKind) {
+        self.current = kind;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+    }
+
+    // Dispara si el arma está lista y tiene balas; devuelve si salió el disparo
+    pub fn fire(&mut self, player: &Player, world: &World, projectiles: &mut Projectiles) -> bool {
+        let stats = self.current.stats();
+        if self.cooldown > 0.0 || self.ammo() == Some(0) {
+            return false;
+        }
+        if stats.max_ammo.is_some() {
+            self.ammo[self.current as usize] -= 1;
+        }
+        self.cooldown = stats.cooldown;
+
+        let z = world.heights.camera_z(player) - MUZZLE_DROP;
+        let first = player.a - stats.spread * (stats.pellets - 1) as f32 / 2.0;
+        for pellet in 0..stats.pellets {
+            let angle = first + stats.spread * pellet as f32;
+            let dir = Vector2::new(angle.cos(), angle.sin());
+            // Con el cañón pegado a una pared delgada o a una reja, la bala no alcanza a
+            // salir: el impacto ocurre entre el jugador y la boca del arma
+            if let Some(distance) = wall_distance(world, player.pos, dir, MUZZLE_OFFSET, z) {
+                projectiles.effects.push(HitEffect { pos: player.pos + dir * (distance - WALL_MARGIN).max(0.0), z, age: 0.0 });
+                continue;
+            }
+            projectiles.shots.push(Projectile { pos: player.pos + dir * MUZZLE_OFFSET, dir, z, kind: self.current, traveled: 0.0, age: 0.0 });
+        }
+        true
+    }
+}
+
+struct Projectile {
+    pos: Vector2<f32>,
+    dir: Vector2<f32>, // Unitario
+    z: f32,            // Altura a la que vuela
+    kind: WeaponKind,
+    traveled: f32,
+    age: f32,
+}
+
+struct HitEffect {
+    pos: Vector2<f32>,
+    z: f32,
+    age: f32,
+}
+
+// Distancia hasta la primera pared que corta el segmento que sale de `from` en dirección
+// `dir` y mide `length`, recorriendo la cuadrícula celda por celda como los rayos. Las
+// paredes bajas y los pisos elevados sólo lo paran si el disparo va por debajo de su borde
+fn wall_distance(world: &World, from: Vector2<f32>, dir: Vector2<f32>, length: f32, z: f32) -> Option<f32> {
+    let World { maze, walls, heights, doors, .. } = world;
+    let mut map_x = from.x.floor() as isize;
+    let mut map_y = from.y.floor() as isize;
+    let step_x = if dir.x >= 0.0 { 1 } else { -1 };
+    let step_y = if dir.y >= 0.0 { 1 } else { -1 };
+    let delta_x = (1.0 / dir.x).abs();
+    let delta_y = (1.0 / dir.y).abs();
+    let mut side_x = if dir.x >= 0.0 { map_x as f32 + 1.0 - from.x } else { from.x - map_x as f32 } * delta_x;
+    let mut side_y = if dir.y >= 0.0 { map_y as f32 + 1.0 - from.y } else { from.y - map_y as f32 } * delta_y;
+    let mut entered = 0.0;
+
+    while entered <= length {
+        if map_y < 0 || map_x < 0 || map_y as usize >= maze.len() || map_x as usize >= maze[map_y as usize].len() {
+            return Some(entered);
+        }
+        let (x, y) = (map_x as usize, map_y as usize);
+
+        let hit = if doors::is_door(maze[y][x]) {
+            doors.get(x, y).and_then(|door| {
+                Panel { vertical: door.vertical, offset: DOOR_OFFSET, start: door.open, end: 1.0 }.intersect(&from, &dir, map_x, map_y).map(|(d, _)| d)
+            })
+        } else {
+            match walls.shape(x, y) {
+                WallShape::Empty => (z < heights.floor(x, y)).then_some(entered),
+                WallShape::Block => (z < heights.wall(x, y)).then_some(entered),
+                shape => shape.intersect(&from, &dir, map_x, map_y).map(|(d, _)| d),
+            }
+        };
+        if let Some(distance) = hit.filter(|&d| d <= length) {
+            return Some(distance);
+        }
+
+        if side_x < side_y {
+            entered = side_x;
+            side_x += delta_x;
+            map_x += step_x;
+        } else {
+            entered = side_y;
+            side_y += delta_y;
+            map_y += step_y;
+        }
+    }
+    None
+}
+
+// Los disparos en vuelo y los estallidos de los impactos
+pub struct Projectiles {
+    shots: Vec<Projectile>,
+    effects: Vec<HitEffect>,
+}
+
+impl Projectiles {
+    pub fn new() -> Self {
+        Projectiles { shots: Vec::new(), effects: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.shots.clear();
+        self.effects.clear();
+    }
+
+    // Mueve los disparos: se detienen en la primera pared o en el primer enemigo que
+    // tocan, y se desvanecen al terminar su alcance. Devuelve cuántos enemigos murieron
+    pub fn update(&mut self, dt: f32, world: &World, enemies: &mut Enemies) -> usize {
+        let mut kills = 0;
+        let mut effects = Vec::new();
+
+        for effect in &mut self.effects {
+            effect.age += dt;
+        }
+        let hit_duration = HIT.get().duration();
+        self.effects.retain(|effect| effect.age < hit_duration);
+
+        self.shots.retain_mut(|shot| {
+            let stats = shot.kind.stats();
+            shot.age += dt;
+            let length = (stats.speed * dt).min(stats.range - shot.traveled);
+            let wall = wall_distance(world, shot.pos, shot.dir, length, shot.z);
+
+            // El enemigo más cercano cuyo sprite cruza el tramo antes de la pared
+            let reach = wall.unwrap_or(length);
+            let target = enemies
+                .list
+                .iter_mut()
+                .filter(|enemy| !enemy.is_dead())
+                .filter_map(|enemy| {
+                    let along = (enemy.pos - shot.pos).dot(&shot.dir).clamp(0.0, reach);
+                    let point = shot.pos + shot.dir * along;
+                    let floor = world.heights.floor(enemy.pos.x as usize, enemy.pos.y as usize);
+                    enemy.contains(point, shot.z, floor).then_some((along, enemy))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            if let Some((along, enemy)) = target {
+                enemy.hit(stats.damage);
+                kills += enemy.is_dead() as usize;
+                effects.push(HitEffect { pos: shot.pos + shot.dir * along, z: shot.z, age: 0.0 });
+                return false;
+            }
+            if let Some(distance) = wall {
+                effects.push(HitEffect { pos: shot.pos + shot.dir * (distance - WALL_MARGIN).max(0.0), z: shot.z, age: 0.0 });
+                return false;
+            }
+
+            shot.pos += shot.dir * length;
+            shot.traveled += length;
+            shot.traveled < stats.range
+        });
+
+        self.effects.extend(effects);
+        kills
+    }
+
+    pub fn sprites(&self) -> impl Iterator<Item = Sprite> + '_ {
+        let shots = self.shots.iter().map(|shot| {
+            let stats = shot.kind.stats();
+            let shape = SpriteShape { height: stats.size, width_scale: 1.0, anchor: Anchor::Fixed(shot.z) };
+            Sprite { pos: shot.pos, texture: SHOTS[shot.kind as usize].get().frame_at(shot.age), shape, phase: 0.0 }
+        });
+        let effects = self.effects.iter().map(|effect| {
+            let shape = SpriteShape { height: HIT_SIZE, width_scale: 1.0, anchor: Anchor::Fixed(effect.z) };
+            Sprite { pos: effect.pos, texture: HIT.get().frame_at(effect.age), shape, phase: 0.0 }
+        });
+        shots.chain(effects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemies::{self, Clip, Enemies};
+    use crate::test_level;
+    use crate::world::World;
+
+    const ROOM: [&str; 3] = ["+-------+", "|       |", "+-------+"];
+    const EAST: Vector2<f32> = Vector2::new(1.0, 0.0);
+
+    fn distance(level: &World, from: Vector2<f32>, dir: Vector2<f32>, length: f32, z: f32) -> Option<f32> {
+        wall_distance(level, from, dir, length, z)
+    }
+
+    fn assert_close(found: Option<f32>, expected: f32) {
+        let Some(found) = found else {
+            panic!("no wall found, expected one at {}", expected);
+        };
+        assert!((found - expected).abs() < 1e-4, "wall at {} instead of {}", found, expected);
+    }
+
+    fn fire(weapons: &mut Weapons, level: &World, player: &Player, projectiles: &mut Projectiles) -> bool {
+        weapons.fire(player, level, projectiles)
+    }
+
+    fn update(projectiles: &mut Projectiles, level: &World, dt: f32, enemies: &mut Enemies) -> usize {
+        projectiles.update(dt, level, enemies)
+    }
+
+    #[test]
+    fn stops_at_blocks_within_length() {
+        let level = test_level::level(&["+-----+", "|     |", "+-----+"]);
+        assert_close(distance(&level, Vector2::new(1.5, 1.5), EAST, 10.0, 0.4), 4.5);
+        assert!(distance(&level, Vector2::new(1.5, 1.5), EAST, 4.0, 0.4).is_none());
+        assert_close(distance(&level, Vector2::new(3.5, 1.5), Vector2::new(0.0, -1.0), 1.0, 0.4), 0.5);
+    }
+
+    #[test]
+    fn hits_thin_panels_and_fences() {
+        let level = test_level::level(&["+-----+", "| ] ! |", "+-----+"]);
+        // Panel pegado al lado este de la celda 2 y valla a la mitad de la celda 4
+        assert_close(distance(&level, Vector2::new(1.5, 1.5), EAST, 10.0, 0.4), 1.4);
+        assert_close(distance(&level, Vector2::new(3.2, 1.5), EAST, 10.0, 0.4), 1.3);
+        assert!(distance(&level, Vector2::new(3.2, 1.5), EAST, 1.0, 0.4).is_none());
+    }
+
+    #[test]
+    fn leaves_the_map_as_a_wall() {
+        let level = test_level::level(&["     "]);
+        assert_close(distance(&level, Vector2::new(2.5, 0.5), EAST, 10.0, 0.4), 2.5);
+    }
+
+    #[test]
+    fn fires_only_with_ammo_and_after_the_cooldown() {
+        let level = test_level::level(&ROOM);
+        let player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        let mut projectiles = Projectiles::new();
+        let mut weapons = Weapons::new();
+        assert_eq!(weapons.ammo(), None);
+
+        weapons.select(WeaponKind::Charge);
+        let Some(max_ammo) = WeaponKind::Charge.stats().max_ammo else {
+            panic!("the charge shot should run out of ammo");
+        };
+        assert!(fire(&mut weapons, &level, &player, &mut projectiles));
+        assert_eq!(weapons.ammo(), Some(max_ammo - 1));
+        assert_eq!(projectiles.shots.len(), 1);
+
+        // Hasta que pasa la espera no sale otro disparo
+        let cooldown = WeaponKind::Charge.stats().cooldown;
+        assert!(!fire(&mut weapons, &level, &player, &mut projectiles));
+        weapons.update(cooldown / 2.0);
+        assert!(!fire(&mut weapons, &level, &player, &mut projectiles));
+        weapons.update(cooldown / 2.0);
+        assert!(fire(&mut weapons, &level, &player, &mut projectiles));
+
+        // Sin balas no dispara, y llenarlas lo deja disparar de nuevo
+        for _ in 2..max_ammo {
+            weapons.update(cooldown);
+            assert!(fire(&mut weapons, &level, &player, &mut projectiles));
+        }
+        weapons.update(cooldown);
+        assert_eq!(weapons.ammo(), Some(0));
+        assert!(!fire(&mut weapons, &level, &player, &mut projectiles));
+        assert_eq!(projectiles.shots.len(), max_ammo as usize);
+
+        weapons.refill();
+        assert_eq!(weapons.ammo(), Some(max_ammo));
+        assert!(fire(&mut weapons, &level, &player, &mut projectiles));
+    }
+
+    #[test]
+    fn shots_hit_the_nearest_enemy_before_the_wall() {
+        let level = test_level::level(&ROOM);
+        let player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        let mut projectiles = Projectiles::new();
+        // El más lejano va primero en la lista para que el orden no decida
+        let mut enemies = Enemies { list: vec![enemies::tests::enemy(Vector2::new(5.5, 1.5), 0.0), enemies::tests::enemy(Vector2::new(3.5, 1.5), 0.0)] };
+
+        assert!(fire(&mut Weapons::new(), &level, &player, &mut projectiles));
+        assert_eq!(update(&mut projectiles, &level, 0.5, &mut enemies), 0);
+        assert!(enemies.list[0].animator.clip() == Clip::Idle);
+        assert!(enemies.list[1].animator.clip() == Clip::Hurt);
+        assert!(projectiles.shots.is_empty());
+        assert_eq!(projectiles.effects.len(), 1);
+        assert!((projectiles.effects[0].pos.x - 3.5).abs() < 1e-4, "hit at {}", projectiles.effects[0].pos.x);
+
+        // Un enemigo detrás de una pared no recibe el disparo
+        let level = test_level::level(&["+-------+", "|   |   |", "+-------+"]);
+        let mut enemies = Enemies { list: vec![enemies::tests::enemy(Vector2::new(5.5, 1.5), 0.0)] };
+        projectiles.clear();
+        assert!(fire(&mut Weapons::new(), &level, &player, &mut projectiles));
+        update(&mut projectiles, &level, 1.0, &mut enemies);
+        assert!(enemies.list[0].animator.clip() == Clip::Idle);
+        assert_eq!(projectiles.effects.len(), 1);
+        assert!((projectiles.effects[0].pos.x - (4.0 - WALL_MARGIN)).abs() < 1e-4, "hit at {}", projectiles.effects[0].pos.x);
+    }
+
+    #[test]
+    fn killed_enemies_die_and_disappear() {
+        let level = test_level::level(&ROOM);
+        let player = Player::new(Vector2::new(1.5, 1.5), 0.0);
+        let mut projectiles = Projectiles::new();
+        let mut enemies = Enemies { list: vec![enemies::tests::enemy(Vector2::new(4.5, 1.5), 0.0)] };
+        let mut weapons = Weapons::new();
+        weapons.select(WeaponKind::Charge);
+
+        let mut kills = 0;
+        for _ in 0..WeaponKind::Charge.stats().max_ammo.unwrap_or(0) {
+            if kills > 0 {
+                break;
+            }
+            weapons.update(WeaponKind::Charge.stats().cooldown);
+            assert!(fire(&mut weapons, &level, &player, &mut projectiles));
+            kills += update(&mut projectiles, &level, 1.0, &mut enemies);
+        }
+        assert_eq!(kills, 1);
+        assert!(enemies.list[0].is_dead());
+        assert!(enemies.list[0].animator.clip() == Clip::Die);
+
+        // Sigue en la lista mientras dura el clip de muerte y después desaparece
+        enemies.update(0.05, &level, &player);
+        assert_eq!(enemies.list.len(), 1);
+        for _ in 0..10 {
+            enemies.update(0.05, &level, &player);
+        }
+        assert!(enemies.list.is_empty());
+    }
+}
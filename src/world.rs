@@ -0,0 +1,27 @@
+use crate::doors::Doors;
+use crate::heights::HeightMap;
+use crate::lighting::Lighting;
+use crate::walls::WallMap;
+
+// El mapa del nivel y todo lo que se arma a partir de él: lo que necesitan el movimiento,
+// los disparos, los enemigos y el dibujo en cada cuadro
+pub struct World {
+    pub maze: Vec<Vec<char>>,
+    pub walls: WallMap,
+    pub heights: HeightMap,
+    pub doors: Doors,
+    pub lighting: Lighting,
+}
+
+impl World {
+    // `heights` es la ruta del mapa de alturas del nivel; vacía si no tiene
+    pub fn new(maze: Vec<Vec<char>>, heights: &str, thin_walls: bool) -> Self {
+        World {
+            walls: WallMap::from_maze(&maze, thin_walls),
+            heights: HeightMap::load(heights, &maze),
+            doors: Doors::from_maze(&maze),
+            lighting: Lighting::bake(&maze),
+            maze,
+        }
+    }
+}